@@ -0,0 +1,301 @@
+// Privileged ACPI helper for Dell G Series Controller
+//
+// Runs as root through pkexec (action com.jose.dellcontroller.acpi) and
+// only performs WMAX calls from a fixed allowlist, so the polkit rule
+// doesn't have to hand out a root shell.
+//
+// Usage: dell-acpi-helper <intel|amd> <METHOD:A0,A1,A2,A3>...
+//   e.g. dell-acpi-helper intel 0x14:0x05,0x32,0x00,0x00
+//
+// Every command is validated before any of them runs. One result line is
+// printed per command, in order, exactly as acpi_call reported it.
+//
+// The allowlist mirrors the commands in dell-core/src/wmax.rs without
+// depending on it (the helper runs as root, so it stays dependency-free);
+// the tests check both accept exactly the same calls.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+use std::process::ExitCode;
+
+const ACPI_CALL_PATH: &str = "/proc/acpi/call";
+/// Shared with dell-core (acpi_backend::ACPI_CALL_LOCK_PATH): every process
+/// flocks it around a write+read pair, since acpi_call has one result buffer
+const ACPI_CALL_LOCK_PATH: &str = "/run/lock/dell-acpi-call.lock";
+const INTEL_ACPI_PATH: &str = "\\_SB.AMWW.WMAX";
+const AMD_ACPI_PATH: &str = "\\_SB.AMW3.WMAX";
+
+const METHOD_GET: u8 = 0x14;
+const METHOD_SET: u8 = 0x15;
+const METHOD_MODEL: u8 = 0x1a;
+const METHOD_G_MODE: u8 = 0x25;
+
+/// Fan IDs the EC uses (0x32 CPU, 0x33 GPU, further fans after them)
+const FAN_IDS: RangeInclusive<u8> = 0x32..=0x37;
+/// Temperature sensor IDs (0x01 CPU, 0x06 GPU, other thermistors)
+const TEMP_SENSOR_IDS: RangeInclusive<u8> = 0x01..=0x1f;
+/// Thermal table codes: USTT modes, G-Mode and manual
+const THERMAL_MODES: [u8; 8] = [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xab, 0x00];
+
+fn is_allowed(method: u8, args: [u8; 4]) -> bool {
+    match (method, args) {
+        (METHOD_MODEL, [0x02, 0x02, 0x00, 0x00]) => true,
+        // Power mode readback
+        (METHOD_GET, [0x0b, 0x00, 0x00, 0x00]) => true,
+        // Temperature (read-only, every ID is probed by sensor discovery)
+        (METHOD_GET, [0x04, sensor, 0x00, 0x00]) => TEMP_SENSOR_IDS.contains(&sensor),
+        // Fan RPM and fan boost readback
+        (METHOD_GET, [0x05 | 0x0c, fan, 0x00, 0x00]) => FAN_IDS.contains(&fan),
+        (METHOD_SET, [0x01, mode, 0x00, 0x00]) => THERMAL_MODES.contains(&mode),
+        (METHOD_SET, [0x02, fan, _, 0x00]) => FAN_IDS.contains(&fan),
+        // Toggle / query G-Mode
+        (METHOD_G_MODE, [0x01 | 0x02, 0x00, 0x00, 0x00]) => true,
+        _ => false,
+    }
+}
+
+fn parse_byte(value: &str) -> Option<u8> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// `METHOD:A0,A1,A2,A3`
+fn parse_command(arg: &str) -> Result<(u8, [u8; 4]), String> {
+    let invalid = || format!("invalid command '{}', expected METHOD:A0,A1,A2,A3", arg);
+
+    let (method, args) = arg.split_once(':').ok_or_else(invalid)?;
+    let method = parse_byte(method).ok_or_else(invalid)?;
+    let bytes = args
+        .split(',')
+        .map(parse_byte)
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(invalid)?;
+    let args: [u8; 4] = bytes.try_into().map_err(|_| invalid())?;
+
+    if !is_allowed(method, args) {
+        return Err(format!("command '{}' is not allowed", arg));
+    }
+    Ok((method, args))
+}
+
+// Existing lock files are opened read-only: they may belong to another
+// user, and flock doesn't need write access
+fn lock_acpi_call() -> io::Result<File> {
+    let file = match File::open(ACPI_CALL_LOCK_PATH) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(ACPI_CALL_LOCK_PATH)?,
+        other => other?,
+    };
+    file.lock()?;
+    Ok(file)
+}
+
+fn acpi_call(path: &str, method: u8, args: [u8; 4]) -> io::Result<String> {
+    let request = format!(
+        "{} 0 0x{:02x} {{0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}}}",
+        path, method, args[0], args[1], args[2], args[3]
+    );
+    let _lock = lock_acpi_call()?;
+
+    OpenOptions::new()
+        .write(true)
+        .open(ACPI_CALL_PATH)?
+        .write_all(request.as_bytes())?;
+
+    let mut result = String::new();
+    OpenOptions::new()
+        .read(true)
+        .open(ACPI_CALL_PATH)?
+        .read_to_string(&mut result)?;
+
+    // Keep the one-line-per-command output contract
+    Ok(result.trim().replace('\n', " "))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let Some((target, commands)) = args.split_first() else {
+        eprintln!("usage: dell-acpi-helper <intel|amd> <METHOD:A0,A1,A2,A3>...");
+        return ExitCode::from(2);
+    };
+
+    let path = match target.as_str() {
+        "intel" => INTEL_ACPI_PATH,
+        "amd" => AMD_ACPI_PATH,
+        other => {
+            eprintln!("dell-acpi-helper: unknown ACPI target '{}'", other);
+            return ExitCode::from(2);
+        }
+    };
+
+    if commands.is_empty() {
+        eprintln!("dell-acpi-helper: no commands given");
+        return ExitCode::from(2);
+    }
+
+    let calls = match commands
+        .iter()
+        .map(|c| parse_command(c))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(calls) => calls,
+        Err(e) => {
+            eprintln!("dell-acpi-helper: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    for (method, args) in calls {
+        match acpi_call(path, method, args) {
+            Ok(result) => {
+                let _ = writeln!(stdout, "{}", result);
+            }
+            Err(e) => {
+                eprintln!("dell-acpi-helper: {}: {}", ACPI_CALL_PATH, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dell_core::wmax::{self, WmaxCommand};
+    use dell_core::PowerMode;
+
+    #[test]
+    fn allowlist_table() {
+        let cases: &[(u8, [u8; 4], bool)] = &[
+            (METHOD_MODEL, [0x02, 0x02, 0x00, 0x00], true),
+            (METHOD_MODEL, [0x02, 0x03, 0x00, 0x00], false),
+            (METHOD_GET, [0x0b, 0x00, 0x00, 0x00], true),
+            (METHOD_GET, [0x0b, 0x01, 0x00, 0x00], false),
+            (METHOD_GET, [0x04, 0x01, 0x00, 0x00], true),
+            (METHOD_GET, [0x04, 0x1f, 0x00, 0x00], true),
+            (METHOD_GET, [0x04, 0x00, 0x00, 0x00], false),
+            (METHOD_GET, [0x04, 0x20, 0x00, 0x00], false),
+            (METHOD_GET, [0x05, 0x32, 0x00, 0x00], true),
+            (METHOD_GET, [0x0c, 0x37, 0x00, 0x00], true),
+            (METHOD_GET, [0x05, 0x38, 0x00, 0x00], false),
+            (METHOD_GET, [0x05, 0x31, 0x00, 0x00], false),
+            (METHOD_SET, [0x01, 0xa0, 0x00, 0x00], true),
+            (METHOD_SET, [0x01, 0xab, 0x00, 0x00], true),
+            (METHOD_SET, [0x01, 0x00, 0x00, 0x00], true),
+            (METHOD_SET, [0x01, 0xa6, 0x00, 0x00], false),
+            (METHOD_SET, [0x01, 0xa0, 0x01, 0x00], false),
+            (METHOD_SET, [0x02, 0x33, 0xff, 0x00], true),
+            (METHOD_SET, [0x02, 0x3f, 0xff, 0x00], false),
+            (METHOD_SET, [0x02, 0x33, 0xff, 0x01], false),
+            (METHOD_G_MODE, [0x01, 0x00, 0x00, 0x00], true),
+            (METHOD_G_MODE, [0x02, 0x00, 0x00, 0x00], true),
+            (METHOD_G_MODE, [0x03, 0x00, 0x00, 0x00], false),
+            (0x13, [0x0b, 0x00, 0x00, 0x00], false),
+            (0x00, [0x00, 0x00, 0x00, 0x00], false),
+        ];
+        for &(method, args, allowed) in cases {
+            assert_eq!(
+                is_allowed(method, args),
+                allowed,
+                "0x{:02x} {:02x?}",
+                method,
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn parse_command_table() {
+        assert_eq!(
+            parse_command("0x14:0x05,0x32,0x00,0x00"),
+            Ok((METHOD_GET, [0x05, 0x32, 0x00, 0x00]))
+        );
+        assert_eq!(
+            parse_command("21:1, 160, 0, 0"),
+            Ok((METHOD_SET, [0x01, 0xa0, 0x00, 0x00]))
+        );
+        let rejected = [
+            // Methods outside the allowlist
+            "0x16:0x01,0x00,0x00,0x00",
+            "0x00:0x00,0x00,0x00,0x00",
+            // Arity
+            "0x14:0x0b,0x00,0x00",
+            "0x14:0x0b,0x00,0x00,0x00,0x00",
+            "0x14:",
+            "0x14",
+            // Out-of-range fan, mode and sensor bytes
+            "0x14:0x05,0x40,0x00,0x00",
+            "0x15:0x02,0x38,0xff,0x00",
+            "0x15:0x01,0xff,0x00,0x00",
+            "0x14:0x04,0x80,0x00,0x00",
+            // Not bytes
+            "0x14:0x0b,0x100,0x00,0x00",
+            "0x14:0x0b,-1,0x00,0x00",
+            "0x14:0x0b,zz,0x00,0x00",
+            "; rm -rf /",
+        ];
+        for arg in rejected {
+            assert!(parse_command(arg).is_err(), "{}", arg);
+        }
+    }
+
+    /// Whether dell-core can send this call
+    fn dell_core_sends(method: u8, args: [u8; 4]) -> bool {
+        match WmaxCommand::decode(method, args) {
+            Some(WmaxCommand::SetPowerMode(code)) => PowerMode::from_code(code).is_some(),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    #[test]
+    fn ranges_match_dell_core() {
+        assert_eq!(
+            ACPI_CALL_LOCK_PATH,
+            dell_core::acpi_backend::ACPI_CALL_LOCK_PATH
+        );
+        assert_eq!(FAN_IDS, wmax::FAN_IDS);
+        assert_eq!(TEMP_SENSOR_IDS, wmax::TEMP_SENSOR_IDS);
+        let mut modes: Vec<u8> = PowerMode::ALL.iter().map(|m| m.code()).collect();
+        let mut allowed = THERMAL_MODES.to_vec();
+        modes.sort();
+        allowed.sort();
+        assert_eq!(allowed, modes);
+    }
+
+    #[test]
+    fn allowlist_matches_dell_core_commands() {
+        for method in 0..=u8::MAX {
+            for a0 in 0..=0x10 {
+                for a1 in 0..=u8::MAX {
+                    for a2 in [0x00, 0x01, 0x80, 0xff] {
+                        for a3 in [0x00, 0x01] {
+                            let args = [a0, a1, a2, a3];
+                            assert_eq!(
+                                is_allowed(method, args),
+                                dell_core_sends(method, args),
+                                "0x{:02x} {:02x?}",
+                                method,
+                                args
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,388 +1,755 @@
 // ACPI control for power and fan management
 // Translated from the ACPI parts of main.py
 
+use crate::acpi_backend::{self, AcpiBackend};
+use crate::acpi_value::{AcpiCallError, AcpiValue};
+use crate::hardware::HardwareInfo;
+use crate::models::{LaptopModel, ModelDatabase};
+use crate::power_mode::PowerMode;
+use crate::sensors::{SensorKind, SensorReading, WMAX_SOURCE};
+use crate::thermal_restore::{self, RestoreTarget};
+use crate::wmax::{FanId, SensorId, WmaxCommand, WmaxSensor};
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
-use std::process::Command;
-use log::{debug, info, error};
-
-#[allow(unused)]
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum LaptopModel {
-    G15_5530,
-    G15_5520,
-    G15_5525,
-    G15_5515,
-    G15_5511,
-    G16_7620,
-    G16_7630,
-    AlienwareM16R1,
-    Unknown,
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Fan boost as currently programmed in the EC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FanState {
+    /// Raw boost value (0-255)
+    pub boost: u8,
+    /// Boost as the 0-100% used by the UI sliders
+    pub percent: u8,
 }
 
-impl LaptopModel {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            Self::G15_5530 => "G15 5530",
-            Self::G15_5520 => "G15 5520",
-            Self::G15_5525 => "G15 5525",
-            Self::G15_5515 => "G15 5515",
-            Self::G15_5511 => "G15 5511",
-            Self::G16_7620 => "G16 7620",
-            Self::G16_7630 => "G16 7630",
-            Self::AlienwareM16R1 => "Alienware M16 R1",
-            Self::Unknown => "Unknown",
+impl FanState {
+    pub fn from_boost(boost: u8) -> Self {
+        Self {
+            boost,
+            percent: ((boost as u32 * 100 + 127) / 255) as u8,
         }
     }
+}
 
-    pub fn supports_keyboard(&self) -> bool {
-        !matches!(self, Self::G16_7630 | Self::Unknown)
-    }
+/// Every fan and temperature reading, taken in one batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SensorReadings {
+    pub fan1_rpm: Option<u32>,
+    pub fan2_rpm: Option<u32>,
+    pub cpu_temp: Option<u32>,
+    pub gpu_temp: Option<u32>,
+    pub fan1_boost: Option<FanState>,
+    pub fan2_boost: Option<FanState>,
+    /// Discovered sensors beyond CPU/GPU temperature and the two fans
+    pub extra: Vec<SensorReading>,
 }
 
-const INTEL_ACPI_PATH: &str = "\\_SB.AMWW.WMAX";
-const AMD_ACPI_PATH: &str = "\\_SB.AMW3.WMAX";
+pub(crate) const INTEL_ACPI_PATH: &str = "\\_SB.AMWW.WMAX";
+pub(crate) const AMD_ACPI_PATH: &str = "\\_SB.AMW3.WMAX";
 
 pub struct AcpiController {
+    backend: Box<dyn AcpiBackend>,
     acpi_path: String,
-    acpi_call_dict: HashMap<String, Vec<String>>,
-    pub power_modes: HashMap<String, String>,
+    pub power_modes: Vec<PowerMode>,
     pub model: LaptopModel,
+    models: ModelDatabase,
+    pub hardware: HardwareInfo,
+    /// Sensors read by `read_all_sensors`, see `discover_sensors`
+    pub sensors: Vec<WmaxSensor>,
+    /// Last thermal mode set other than Manual, see `track_last_mode`
+    last_auto_mode: Option<PowerMode>,
+    last_mode_file: Option<PathBuf>,
 }
 
 impl AcpiController {
     pub fn new() -> Result<Self> {
+        Self::with_backend(acpi_backend::default_backend())
+    }
+
+    pub fn with_backend(backend: Box<dyn AcpiBackend>) -> Result<Self> {
+        Self::with_models(backend, ModelDatabase::load_default(), HardwareInfo::detect())
+    }
+
+    /// Controller matching `hardware` against `models` instead of the
+    /// running system and the default database
+    pub fn with_models(
+        backend: Box<dyn AcpiBackend>,
+        models: ModelDatabase,
+        hardware: HardwareInfo,
+    ) -> Result<Self> {
+        let unknown = LaptopModel::unknown();
         let mut controller = Self {
-            acpi_path: INTEL_ACPI_PATH.to_string(),
-            acpi_call_dict: HashMap::new(),
-            power_modes: Self::default_power_modes(),
-            model: LaptopModel::Unknown,
+            backend,
+            acpi_path: unknown.acpi_path.clone(),
+            power_modes: unknown.power_modes.clone(),
+            model: unknown,
+            models,
+            hardware,
+            sensors: WmaxSensor::BASIC.to_vec(),
+            last_auto_mode: None,
+            last_mode_file: None,
         };
+        controller.drop_unsupported_modes();
 
-        controller.setup_acpi_calls();
         controller.detect_model()?;
 
         Ok(controller)
     }
 
-    fn default_power_modes() -> HashMap<String, String> {
-        let mut modes = HashMap::new();
-        modes.insert("USTT_Balanced".to_string(), "0xa0".to_string());
-        modes.insert("USTT_Performance".to_string(), "0xa1".to_string());
-        modes.insert("USTT_Quiet".to_string(), "0xa3".to_string());
-        modes.insert("USTT_FullSpeed".to_string(), "0xa4".to_string());
-        modes.insert("USTT_BatterySaver".to_string(), "0xa5".to_string());
-        modes.insert("G Mode".to_string(), "0xab".to_string());
-        modes.insert("Manual".to_string(), "0x0".to_string());
-        modes
+    /// Controller for an already known ACPI path and mode list, without
+    /// reading DMI or probing the firmware (used by the exit watchdog)
+    pub fn for_restore(
+        backend: Box<dyn AcpiBackend>,
+        target: &RestoreTarget,
+        last_auto_mode: Option<PowerMode>,
+    ) -> Self {
+        let mut model = LaptopModel::unknown();
+        model.acpi_path = target.acpi_path.clone();
+        model.power_modes = target.power_modes.clone();
+        Self {
+            backend,
+            acpi_path: model.acpi_path.clone(),
+            power_modes: model.power_modes.clone(),
+            model,
+            models: ModelDatabase::default(),
+            hardware: HardwareInfo::default(),
+            sensors: WmaxSensor::BASIC.to_vec(),
+            last_auto_mode,
+            last_mode_file: None,
+        }
     }
 
-    fn setup_acpi_calls(&mut self) {
-        self.acpi_call_dict.insert(
-            "get_laptop_model".to_string(),
-            vec!["0x1a".to_string(), "0x02".to_string(), "0x02".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_power_mode".to_string(),
-            vec!["0x14".to_string(), "0x0b".to_string(), "0x00".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "set_power_mode".to_string(),
-            vec!["0x15".to_string(), "0x01".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "toggle_G_mode".to_string(),
-            vec!["0x25".to_string(), "0x01".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_G_mode".to_string(),
-            vec!["0x25".to_string(), "0x02".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "set_fan1_boost".to_string(),
-            vec!["0x15".to_string(), "0x02".to_string(), "0x32".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_fan1_boost".to_string(),
-            vec!["0x14".to_string(), "0x0c".to_string(), "0x32".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_fan1_rpm".to_string(),
-            vec!["0x14".to_string(), "0x05".to_string(), "0x32".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_cpu_temp".to_string(),
-            vec!["0x14".to_string(), "0x04".to_string(), "0x01".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "set_fan2_boost".to_string(),
-            vec!["0x15".to_string(), "0x02".to_string(), "0x33".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_fan2_boost".to_string(),
-            vec!["0x14".to_string(), "0x0c".to_string(), "0x33".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_fan2_rpm".to_string(),
-            vec!["0x14".to_string(), "0x05".to_string(), "0x33".to_string()],
-        );
-        self.acpi_call_dict.insert(
-            "get_gpu_temp".to_string(),
-            vec!["0x14".to_string(), "0x04".to_string(), "0x06".to_string()],
-        );
+    fn set_model(&mut self, model: LaptopModel) {
+        self.acpi_path = model.acpi_path.clone();
+        self.power_modes = model.power_modes.clone();
+        self.drop_unsupported_modes();
+        info!("Model detected: {} ({})", model.name, model.acpi_path);
+        self.model = model;
     }
 
-
-    fn set_model(&mut self, model: LaptopModel, is_amd: bool) {
-        self.model = model;
-        self.acpi_path = if is_amd {
-            AMD_ACPI_PATH.to_string()
-        } else {
-            INTEL_ACPI_PATH.to_string()
-        };
-        self.apply_model_patch();
-        info!("Model detected: {} ({})", model.as_str(), if is_amd { "AMD" } else { "Intel" });
+    // Only offer the modes the backend can actually switch to
+    fn drop_unsupported_modes(&mut self) {
+        let backend = &self.backend;
+        self.power_modes.retain(|m| backend.supports_mode(*m));
     }
 
     fn detect_model(&mut self) -> Result<()> {
         // Try DMI detection first (works without root)
-        if let Ok(output) = Command::new("cat")
-            .arg("/sys/class/dmi/id/product_name")
-            .output()
-        {
-            let product = String::from_utf8_lossy(&output.stdout).to_lowercase();
-            info!("DMI product name: {}", product.trim());
-
-            let detected = match () {
-                _ if product.contains("g15") && product.contains("5530") => Some((LaptopModel::G15_5530, false)),
-                _ if product.contains("g15") && product.contains("5520") => Some((LaptopModel::G15_5520, false)),
-                _ if product.contains("g15") && product.contains("5525") => Some((LaptopModel::G15_5525, true)),
-                _ if product.contains("g15") && product.contains("5515") => Some((LaptopModel::G15_5515, true)),
-                _ if product.contains("g15") && product.contains("5511") => Some((LaptopModel::G15_5511, false)),
-                _ if product.contains("g16") && product.contains("7630") => Some((LaptopModel::G16_7630, false)),
-                _ if product.contains("g16") && product.contains("7620") => Some((LaptopModel::G16_7620, false)),
-                _ => None,
-            };
-
-            if let Some((model, is_amd)) = detected {
-                self.set_model(model, is_amd);
-                return Ok(());
-            }
+        let hw = &self.hardware;
+        info!(
+            "DMI: {} {} (SKU {}, board {}, BIOS {}), CPU {}",
+            hw.sys_vendor,
+            hw.product_name,
+            hw.product_sku,
+            hw.board_name,
+            hw.bios_version,
+            hw.cpu_vendor
+        );
 
-            if product.contains("g15") || product.contains("g16") {
-                info!("Generic Dell G-series detected, probing ACPI interface...");
-            }
+        if let Some(model) = self.models.match_hardware(&self.hardware).cloned() {
+            self.set_model(model);
+            return Ok(());
         }
 
-        // Fallback: ACPI probing - try Intel path first
-        self.acpi_path = INTEL_ACPI_PATH.to_string();
-        match self.acpi_call("get_laptop_model", None, None) {
-            Ok(model_str) => {
-                let model = model_str.trim();
-                debug!("ACPI model probe (Intel): {}", model);
-                match model {
-                    "0x0" => { self.set_model(LaptopModel::G15_5530, false); return Ok(()); }
-                    "0x12c0" => { self.set_model(LaptopModel::G15_5520, false); return Ok(()); }
-                    "0xc80" => { self.set_model(LaptopModel::G15_5511, false); return Ok(()); }
-                    _ => {}
-                }
-            }
-            Err(e) => debug!("Intel ACPI probe failed: {}", e),
+        let product = self.hardware.product_name.to_lowercase();
+        if product.contains("g15") || product.contains("g16") {
+            info!("Generic Dell G-series detected, probing ACPI interface...");
         }
 
-        // Try AMD path
-        self.acpi_path = AMD_ACPI_PATH.to_string();
-        match self.acpi_call("get_laptop_model", None, None) {
-            Ok(model_str) => {
-                let model = model_str.trim();
-                debug!("ACPI model probe (AMD): {}", model);
-                match model {
-                    "0x12c0" => { self.set_model(LaptopModel::G15_5525, true); return Ok(()); }
-                    "0xc80" => { self.set_model(LaptopModel::G15_5515, true); return Ok(()); }
-                    _ => info!("Could not determine specific model, using generic configuration"),
+        // Fallback: ACPI probing on each known WMAX path, starting with
+        // the one matching the CPU vendor
+        let mut paths: Vec<String> =
+            self.models.acpi_paths().into_iter().map(String::from).collect();
+        if self.hardware.is_amd() {
+            paths.sort_by_key(|p| p != AMD_ACPI_PATH);
+        }
+        let mut answered = None;
+        for path in paths {
+            self.acpi_path = path.clone();
+            match self.query_integer(WmaxCommand::GetLaptopModel) {
+                Ok(code) => {
+                    debug!("ACPI model probe ({}): 0x{:x}", path, code);
+                    if let Some(model) = self.models.match_code(&path, code).cloned() {
+                        self.set_model(model);
+                        return Ok(());
+                    }
+                    answered.get_or_insert(path);
                 }
-            }
-            Err(e) => {
-                debug!("AMD ACPI probe failed: {}", e);
-                info!("Could not detect model via ACPI, using generic configuration");
+                Err(e) => debug!("ACPI probe on {} failed: {}", path, e),
             }
         }
 
+        // Keep talking to whichever WMAX object exists
+        self.acpi_path = answered.unwrap_or_else(|| self.model.acpi_path.clone());
+        info!("Could not determine specific model, using generic configuration");
         Ok(())
     }
 
-    fn apply_model_patch(&mut self) {
-        match self.model {
-            LaptopModel::G15_5530 | LaptopModel::G15_5520 => {
-                self.power_modes.remove("USTT_FullSpeed");
-            }
-            LaptopModel::G15_5515 => {
-                self.power_modes.remove("USTT_Balanced");
-                self.power_modes.remove("USTT_Performance");
-                self.power_modes.remove("USTT_Quiet");
-                self.power_modes.remove("USTT_FullSpeed");
-                self.power_modes.remove("USTT_BatterySaver");
-            }
-            LaptopModel::G15_5511 => {
-                self.power_modes.remove("USTT_FullSpeed");
-                self.power_modes.remove("USTT_BatterySaver");
-                self.power_modes.insert("USTT_Cool".to_string(), "0xa2".to_string());
-            }
-            LaptopModel::G16_7630 => {
-                self.power_modes.remove("USTT_FullSpeed");
-            }
-            _ => {}
+    /// WMAX object the commands go to
+    pub fn acpi_path(&self) -> &str {
+        &self.acpi_path
+    }
+
+    pub fn acpi_call(&mut self, cmd: WmaxCommand) -> Result<String> {
+        debug!("ACPI command: {:?}", cmd);
+        let result = self.backend.call(&self.acpi_path, cmd.method(), cmd.args())?;
+        debug!("ACPI result [{:?}]: {}", cmd, result);
+        Ok(result)
+    }
+
+    /// Run a command and parse its result
+    pub fn query(&mut self, cmd: WmaxCommand) -> Result<AcpiValue> {
+        let result = self.acpi_call(cmd)?;
+        Ok(AcpiValue::parse(&result)?)
+    }
+
+    /// Run several commands in one backend invocation. The outer error is
+    /// the invocation failing; each result is parsed on its own.
+    pub fn query_batch(
+        &mut self,
+        cmds: &[WmaxCommand],
+    ) -> Result<Vec<Result<AcpiValue, AcpiCallError>>> {
+        debug!("ACPI batch: {:?}", cmds);
+        let calls: Vec<_> = cmds.iter().map(|c| (c.method(), c.args())).collect();
+        let results = self.backend.call_batch(&self.acpi_path, &calls)?;
+        debug!("ACPI batch results: {:?}", results);
+        Ok(results.iter().map(|r| AcpiValue::parse(r)).collect())
+    }
+
+    /// Sensor readings for `cmds` from one backend call, `None` for the
+    /// ones that failed or that the firmware doesn't report
+    pub fn read_batch(&mut self, cmds: &[WmaxCommand]) -> Result<Vec<Option<u32>>> {
+        let results = self.query_batch(cmds)?;
+        Ok(cmds
+            .iter()
+            .zip(&results)
+            .map(|(cmd, result)| batch_reading(*cmd, result))
+            .collect())
+    }
+
+    fn query_integer(&mut self, cmd: WmaxCommand) -> Result<u64> {
+        let value = self.query(cmd)?;
+        value
+            .as_integer()
+            .ok_or_else(|| anyhow!("Unexpected response to {:?}: {:?}", cmd, value))
+    }
+
+    /// Remember the last mode other than Manual in `path`, so it can be
+    /// put back on exit, even from another process. Starts from the saved
+    /// mode, or the current one if the firmware isn't in Manual.
+    pub fn track_last_mode(&mut self, path: PathBuf) {
+        self.last_auto_mode = thermal_restore::load_last_mode(&path);
+        self.last_mode_file = Some(path);
+        match self.current_power_mode() {
+            Ok(mode) => self.record_mode(mode),
+            Err(e) => debug!("Could not read the current power mode: {}", e),
         }
     }
 
-    pub fn acpi_call(&mut self, cmd: &str, arg1: Option<&str>, arg2: Option<&str>) -> Result<String> {
-        let args = self
-            .acpi_call_dict
-            .get(cmd)
-            .ok_or_else(|| anyhow!("Unknown ACPI command: {}", cmd))?;
+    pub fn last_auto_mode(&self) -> Option<PowerMode> {
+        self.last_auto_mode
+    }
 
-        let cmd_str = match args.len() {
-            4 => format!("echo \"{} 0 {} {{{}, {}, {}, 0x00}}\" > /proc/acpi/call", self.acpi_path, args[0], args[1], args[2], args[3]),
-            3 => {
-                let a1 = arg1.unwrap_or("0x00");
-                format!("echo \"{} 0 {} {{{}, {}, {}, 0x00}}\" > /proc/acpi/call", self.acpi_path, args[0], args[1], args[2], a1)
-            }
-            2 => {
-                let a1 = arg1.unwrap_or("0x00");
-                let a2 = arg2.unwrap_or("0x00");
-                format!("echo \"{} 0 {} {{{}, {}, {}, 0x00}}\" > /proc/acpi/call", self.acpi_path, args[0], args[1], a1, a2)
+    fn record_mode(&mut self, mode: PowerMode) {
+        if mode == PowerMode::Manual || self.last_auto_mode == Some(mode) {
+            return;
+        }
+        self.last_auto_mode = Some(mode);
+        if let Some(path) = &self.last_mode_file {
+            if let Err(e) = thermal_restore::save_last_mode(path, mode) {
+                warn!("Failed to save the last power mode: {}", e);
             }
-            _ => return Err(anyhow!("Invalid ACPI call format")),
-        };
+        }
+    }
+
+    pub fn set_power_mode(&mut self, mode: PowerMode) -> Result<()> {
+        info!("Setting power mode: {} (ACPI path: {})", mode.as_str(), self.acpi_path);
 
-        debug!("ACPI command [{}]: {}", cmd, cmd_str);
+        if mode == PowerMode::GMode {
+            return self.set_g_mode(true);
+        }
 
-        let output = Command::new("pkexec")
-            .arg("sh")
-            .arg("-c")
-            .arg(&format!("{}; cat /proc/acpi/call", cmd_str))
-            .output()?;
+        if !self.power_modes.contains(&mode) {
+            return Err(anyhow!(
+                "Mode '{}' is not supported on this model. Available modes: {:?}",
+                mode.as_str(),
+                self.power_modes.iter().map(|m| m.as_str()).collect::<Vec<_>>()
+            ));
+        }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("dismissed") || stderr.contains("Not authorized") {
-                error!("Autorizacao cancelada pelo usuario para: {}", cmd);
-                return Err(anyhow!("Autorizacao cancelada. Aceite a janela de autorizacao para continuar."));
-            }
-            error!("ACPI call '{}' failed: {}", cmd, stderr);
-            return Err(anyhow!("Falha no comando ACPI '{}': {}", cmd, stderr));
+        // Leaving G-Mode through another mode must also clear the G-Mode flag
+        if self.power_modes.contains(&PowerMode::GMode) && self.g_mode_enabled().unwrap_or(false) {
+            self.acpi_call(WmaxCommand::ToggleGMode)?;
+        }
+
+        info!("Power mode '{}' -> ACPI value: 0x{:02x}", mode.as_str(), mode.code());
+        self.acpi_call(WmaxCommand::SetPowerMode(mode.code()))?;
+        info!("Power mode '{}' applied", mode.as_str());
+        self.record_mode(mode);
+        Ok(())
+    }
+
+    /// Read the active thermal mode back from the firmware
+    pub fn current_power_mode(&mut self) -> Result<PowerMode> {
+        let code = self.query_integer(WmaxCommand::GetPowerMode)?;
+        u8::try_from(code)
+            .ok()
+            .and_then(PowerMode::from_code)
+            .ok_or_else(|| anyhow!("Unknown power mode: 0x{:x}", code))
+    }
+
+    /// Enable or disable G-Mode the way the G key does on Windows: the
+    /// G-Mode thermal table plus the firmware's G-Mode flag
+    pub fn set_g_mode(&mut self, enable: bool) -> Result<()> {
+        if !self.power_modes.contains(&PowerMode::GMode) {
+            return Err(anyhow!("G-Mode is not supported on this model"));
         }
 
-        let result = String::from_utf8_lossy(&output.stdout);
-        debug!("ACPI result [{}]: {}", cmd, result.trim());
+        let enabled = self.g_mode_enabled()?;
+        info!("Setting G-Mode: {} (currently {})", enable, enabled);
 
-        let lines: Vec<&str> = result.lines().collect();
-        if let Some(last_line) = lines.last() {
-            let trimmed = last_line.trim().trim_end_matches('%').trim_matches('\'');
-            Ok(trimmed.to_string())
+        if enable {
+            self.acpi_call(WmaxCommand::SetPowerMode(PowerMode::GMode.code()))?;
+            if !enabled {
+                self.acpi_call(WmaxCommand::ToggleGMode)?;
+            }
+            self.record_mode(PowerMode::GMode);
         } else {
-            Err(anyhow!("Sem resposta do comando ACPI '{}'", cmd))
+            if enabled {
+                self.acpi_call(WmaxCommand::ToggleGMode)?;
+            }
+            let fallback = self
+                .power_modes
+                .iter()
+                .copied()
+                .find(|m| !matches!(m, PowerMode::GMode | PowerMode::Manual))
+                .unwrap_or(PowerMode::Manual);
+            self.acpi_call(WmaxCommand::SetPowerMode(fallback.code()))?;
+            self.record_mode(fallback);
         }
+        Ok(())
     }
 
-    pub fn set_power_mode(&mut self, mode: &str) -> Result<()> {
-        info!("Setting power mode: {} (ACPI path: {})", mode, self.acpi_path);
-
-        let mode_value = self
-            .power_modes
-            .get(mode)
-            .ok_or_else(|| anyhow!("Modo desconhecido: '{}'. Modos disponiveis: {:?}", mode, self.power_modes.keys().collect::<Vec<_>>()))?
-            .clone();
+    pub fn g_mode_enabled(&mut self) -> Result<bool> {
+        Ok(self.query_integer(WmaxCommand::GetGMode)? != 0)
+    }
 
-        info!("Power mode '{}' -> ACPI value: {}", mode, mode_value);
-        self.acpi_call("set_power_mode", Some(&mode_value), None)?;
-        info!("Power mode '{}' aplicado com sucesso", mode);
+    pub fn set_fan_boost(&mut self, fan: FanId, boost: u8) -> Result<()> {
+        debug!("Setting {:?} boost to {}", fan, boost);
+        self.acpi_call(WmaxCommand::SetFanBoost(fan, boost))
+            .map_err(|e| anyhow!("Fan control failed for {:?}: {}", fan, e))?;
         Ok(())
     }
 
-    #[allow(unused)]
-    pub fn get_power_mode(&mut self) -> Result<String> {
-        self.acpi_call("get_power_mode", None, None)
+    pub fn get_fan_boost(&mut self, fan: FanId) -> Result<FanState> {
+        let value = self.query_integer(WmaxCommand::GetFanBoost(fan))?;
+        let boost = u8::try_from(value)
+            .map_err(|_| anyhow!("Invalid boost for {:?}: 0x{:x}", fan, value))?;
+        Ok(FanState::from_boost(boost))
     }
 
-    pub fn set_fan_boost(&mut self, fan_id: u8, boost: u8) -> Result<()> {
-        let fan_code = if fan_id == 1 { "32" } else { "33" };
-        let cmd = format!("echo \"{} 0 0x15 {{0x02, 0x{}, 0x{:02X}, 0x00}}\" > /proc/acpi/call",
-                         self.acpi_path, fan_code, boost);
+    /// Fan speed in RPM, `None` if the firmware reports no such fan
+    pub fn get_fan_rpm(&mut self, fan: FanId) -> Result<Option<u32>> {
+        Ok(self.query(WmaxCommand::GetFanRpm(fan))?.reading()?)
+    }
 
-        debug!("Setting fan {} boost to {}: {}", fan_id, boost, cmd);
+    /// Temperature in °C, `None` if the firmware reports no such sensor
+    pub fn get_temp(&mut self, sensor: SensorId) -> Result<Option<u32>> {
+        Ok(self.query(WmaxCommand::GetTemp(sensor))?.reading()?)
+    }
 
-        let output = Command::new("pkexec")
-            .arg("sh")
-            .arg("-c")
-            .arg(&format!("{}; cat /proc/acpi/call", cmd))
-            .output()?;
+    /// CPU and GPU temperatures in °C from one backend call
+    pub fn read_temperatures(&mut self) -> Result<(Option<u32>, Option<u32>)> {
+        let temps = self.read_batch(&[
+            WmaxCommand::GetTemp(SensorId::Cpu),
+            WmaxCommand::GetTemp(SensorId::Gpu),
+        ])?;
+        Ok((temps[0], temps[1]))
+    }
+
+    /// CPU and GPU fan speeds in RPM from one backend call
+    pub fn read_fan_rpms(&mut self) -> Result<(Option<u32>, Option<u32>)> {
+        let rpms = self.read_batch(&[
+            WmaxCommand::GetFanRpm(FanId::Fan1),
+            WmaxCommand::GetFanRpm(FanId::Fan2),
+        ])?;
+        Ok((rpms[0], rpms[1]))
+    }
 
-        if output.status.success() {
-            Ok(())
+    /// Probe every WMAX temperature sensor and fan ID in one batch and
+    /// remember the ones that answer. Keeps the current list if the probe
+    /// finds nothing (e.g. the backend can't run arbitrary IDs).
+    pub fn discover_sensors(&mut self) -> Result<&[WmaxSensor]> {
+        let candidates = WmaxSensor::probe_list();
+        let commands: Vec<WmaxCommand> = candidates.iter().map(|s| s.command()).collect();
+        let results = self.query_batch(&commands)?;
+        // Fan count from the database; anything can answer on unknown models
+        let fans = (!self.model.is_unknown()).then_some(self.model.fans);
+
+        let found: Vec<WmaxSensor> = candidates
+            .into_iter()
+            .zip(results)
+            .filter(|(sensor, result)| {
+                let reading = batch_reading(sensor.command(), result);
+                match sensor {
+                    // Unpopulated thermistors read 0
+                    WmaxSensor::Temp(_) => reading.is_some_and(|t| t > 0),
+                    // A stopped fan legitimately reads 0 RPM, but some
+                    // firmware answers 0 for fan IDs that aren't populated,
+                    // so extra fans only count once they're seen spinning
+                    WmaxSensor::Fan(FanId::Fan1 | FanId::Fan2) => reading.is_some(),
+                    WmaxSensor::Fan(fan @ FanId::Extra(_)) => {
+                        fans.is_none_or(|n| fan.index() <= n) && reading.is_some_and(|rpm| rpm > 0)
+                    }
+                }
+            })
+            .map(|(sensor, _)| sensor)
+            .collect();
+
+        if found.is_empty() {
+            info!("Sensor probe found nothing, keeping {} sensors", self.sensors.len());
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("dismissed") || stderr.contains("Not authorized") {
-                return Err(anyhow!("Autorizacao cancelada. Aceite a janela de autorizacao."));
-            }
-            error!("Fan boost failed (fan {}): {}", fan_id, stderr);
-            Err(anyhow!("Falha no controle do ventilador {}: {}", fan_id, stderr))
+            info!(
+                "Discovered WMAX sensors: {:?}",
+                found.iter().map(|s| s.name()).collect::<Vec<_>>()
+            );
+            self.sensors = found;
         }
+        Ok(&self.sensors)
     }
 
-    pub fn get_fan_rpm(&mut self, fan_id: u8) -> Result<u32> {
-        let cmd = match fan_id {
-            1 => "get_fan1_rpm",
-            2 => "get_fan2_rpm",
-            _ => return Err(anyhow!("Invalid fan ID")),
+    /// Fan speeds, temperatures and fan boosts from a single privileged call.
+    /// Readings the firmware doesn't report come back as `None`.
+    pub fn read_all_sensors(&mut self) -> Result<SensorReadings> {
+        let extra: Vec<WmaxSensor> = self
+            .sensors
+            .iter()
+            .copied()
+            .filter(|s| !WmaxSensor::BASIC.contains(s))
+            .collect();
+
+        let mut commands = vec![
+            WmaxCommand::GetFanRpm(FanId::Fan1),
+            WmaxCommand::GetFanRpm(FanId::Fan2),
+            WmaxCommand::GetTemp(SensorId::Cpu),
+            WmaxCommand::GetTemp(SensorId::Gpu),
+            WmaxCommand::GetFanBoost(FanId::Fan1),
+            WmaxCommand::GetFanBoost(FanId::Fan2),
+        ];
+        commands.extend(extra.iter().map(|s| s.command()));
+        let results = self.query_batch(&commands)?;
+
+        let reading = |i: usize| batch_reading(commands[i], &results[i]);
+        let boost = |i: usize| {
+            results[i]
+                .as_ref()
+                .ok()
+                .and_then(AcpiValue::as_integer)
+                .and_then(|v| u8::try_from(v).ok())
+                .map(FanState::from_boost)
         };
-        
-        let result = self.acpi_call(cmd, None, None)?;
-        
-        // Check for error values
-        if result == "0xffffffff" {
-            return Ok(0); // Return 0 for unavailable sensors
+
+        let extra = extra
+            .iter()
+            .enumerate()
+            .filter_map(|(i, sensor)| {
+                let value = reading(6 + i)?;
+                let (id, kind) = match sensor {
+                    WmaxSensor::Temp(t) => {
+                        (format!("temp_0x{:02x}", t.code()), SensorKind::Temperature)
+                    }
+                    WmaxSensor::Fan(f) => (format!("fan_0x{:02x}", f.code()), SensorKind::Fan),
+                };
+                Some(SensorReading::new(
+                    format!("{}:{}", WMAX_SOURCE, id),
+                    sensor.name(),
+                    kind,
+                    value as f64,
+                    WMAX_SOURCE,
+                ))
+            })
+            .collect();
+
+        Ok(SensorReadings {
+            fan1_rpm: reading(0),
+            fan2_rpm: reading(1),
+            cpu_temp: reading(2),
+            gpu_temp: reading(3),
+            fan1_boost: boost(4),
+            fan2_boost: boost(5),
+            extra,
+        })
+    }
+}
+
+/// Reading from one `query_batch` result, `None` (logged) if the call failed
+/// or the firmware doesn't report the sensor
+fn batch_reading(cmd: WmaxCommand, result: &Result<AcpiValue, AcpiCallError>) -> Option<u32> {
+    match result.as_ref().map_err(Clone::clone).and_then(AcpiValue::reading) {
+        Ok(reading) => reading,
+        Err(e) => {
+            debug!("Sensor read {:?} failed: {}", cmd, e);
+            None
         }
-        
-        // Clean the result: remove 0x prefix, null bytes, and any non-hex characters
-        let clean_result = result
-            .trim_start_matches("0x")
-            .trim_end_matches('\0')
-            .trim()
-            .chars()
-            .take_while(|c| c.is_ascii_hexdigit())
-            .collect::<String>();
-        
-        // Parse hex directly
-        let rpm = u32::from_str_radix(&clean_result, 16)?;
-        Ok(rpm)
-    }
-
-    pub fn get_temp(&mut self, sensor: &str) -> Result<u32> {
-        let cmd = match sensor {
-            "cpu" => "get_cpu_temp",
-            "gpu" => "get_gpu_temp",
-            _ => return Err(anyhow!("Invalid sensor")),
-        };
-        
-        let result = self.acpi_call(cmd, None, None)?;
-        
-        // Check for error values
-        if result == "0xffffffff" {
-            return Ok(0); // Return 0 for unavailable sensors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acpi_backend::FakeAcpiBackend;
+
+    fn hardware(product: &str, cpu_vendor: &str) -> HardwareInfo {
+        HardwareInfo {
+            sys_vendor: "Dell Inc.".to_string(),
+            product_name: product.to_string(),
+            cpu_vendor: cpu_vendor.to_string(),
+            ..HardwareInfo::default()
         }
-        
-        // Clean the result: remove 0x prefix, null bytes, and any non-hex characters
-        let clean_result = result
-            .trim_start_matches("0x")
-            .trim_end_matches('\0')
-            .trim()
-            .chars()
-            .take_while(|c| c.is_ascii_hexdigit())
-            .collect::<String>();
-        
-        // Parse hex directly
-        let temp = u32::from_str_radix(&clean_result, 16)?;
-        Ok(temp)
+    }
+
+    fn controller_for(fake: &FakeAcpiBackend, product: &str, cpu_vendor: &str) -> AcpiController {
+        AcpiController::with_models(
+            Box::new(fake.clone()),
+            ModelDatabase::embedded(),
+            hardware(product, cpu_vendor),
+        )
+        .unwrap()
+    }
+
+    fn respond(fake: &FakeAcpiBackend, cmd: WmaxCommand, result: &str) {
+        fake.respond(cmd.method(), cmd.args(), result);
+    }
+
+    fn sent(fake: &FakeAcpiBackend) -> Vec<WmaxCommand> {
+        fake.calls()
+            .iter()
+            .filter_map(|c| WmaxCommand::decode(c.method, c.args))
+            .collect()
+    }
+
+    #[test]
+    fn detects_model_from_dmi_without_probing() {
+        let fake = FakeAcpiBackend::new();
+        let controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+        assert_eq!(controller.model.id, "g15-5520");
+        assert_eq!(controller.acpi_path, INTEL_ACPI_PATH);
+        assert!(fake.calls().is_empty());
+    }
+
+    #[test]
+    fn cpu_vendor_picks_the_amd_variant() {
+        let fake = FakeAcpiBackend::new();
+        let amd = controller_for(&fake, "Dell G15 5511", "AuthenticAMD");
+        assert_eq!(amd.model.id, "g15-5511-amd");
+        assert_eq!(amd.acpi_path, AMD_ACPI_PATH);
+
+        let intel = controller_for(&fake, "Dell G15 5511", "GenuineIntel");
+        assert_eq!(intel.model.id, "g15-5511");
+    }
+
+    #[test]
+    fn probes_model_code_when_dmi_is_unknown() {
+        let fake = FakeAcpiBackend::new();
+        let probe = WmaxCommand::GetLaptopModel;
+        fake.respond_at(AMD_ACPI_PATH, probe.method(), probe.args(), "0xc80");
+
+        let controller = controller_for(&fake, "Dell Gaming Laptop", "GenuineIntel");
+        assert_eq!(controller.model.id, "g15-5515");
+        assert_eq!(controller.acpi_path, AMD_ACPI_PATH);
+        // Intel path first for an Intel CPU
+        let paths: Vec<String> = fake.calls().into_iter().map(|c| c.path).collect();
+        assert_eq!(paths, [INTEL_ACPI_PATH, AMD_ACPI_PATH]);
+    }
+
+    #[test]
+    fn unknown_model_keeps_the_path_that_answered() {
+        let fake = FakeAcpiBackend::new();
+        let probe = WmaxCommand::GetLaptopModel;
+        fake.respond_at(AMD_ACPI_PATH, probe.method(), probe.args(), "0x4242");
+
+        let controller = controller_for(&fake, "Something Else", "AuthenticAMD");
+        assert!(controller.model.is_unknown());
+        assert_eq!(controller.acpi_path, AMD_ACPI_PATH);
+    }
+
+    #[test]
+    fn set_power_mode_sends_the_thermal_code() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetGMode, "0x0");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        controller.set_power_mode(PowerMode::Performance).unwrap();
+        assert_eq!(
+            sent(&fake),
+            [WmaxCommand::GetGMode, WmaxCommand::SetPowerMode(0xa1)]
+        );
+    }
+
+    #[test]
+    fn leaving_g_mode_clears_the_flag_first() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetGMode, "0x1");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        controller.set_power_mode(PowerMode::Balanced).unwrap();
+        assert_eq!(
+            sent(&fake),
+            [
+                WmaxCommand::GetGMode,
+                WmaxCommand::ToggleGMode,
+                WmaxCommand::SetPowerMode(0xa0)
+            ]
+        );
+    }
+
+    #[test]
+    fn g_mode_sets_table_and_flag() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetGMode, "0x0");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        controller.set_power_mode(PowerMode::GMode).unwrap();
+        assert_eq!(
+            sent(&fake),
+            [
+                WmaxCommand::GetGMode,
+                WmaxCommand::SetPowerMode(0xab),
+                WmaxCommand::ToggleGMode
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_mode_is_rejected_before_any_call() {
+        let fake = FakeAcpiBackend::new();
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        assert!(controller.set_power_mode(PowerMode::Cool).is_err());
+        assert!(fake.calls().is_empty());
+    }
+
+    #[test]
+    fn power_mode_readback() {
+        let fake = FakeAcpiBackend::new();
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        respond(&fake, WmaxCommand::GetPowerMode, "0xa3");
+        assert_eq!(controller.current_power_mode().unwrap(), PowerMode::Quiet);
+        respond(&fake, WmaxCommand::GetPowerMode, "0x0");
+        assert_eq!(controller.current_power_mode().unwrap(), PowerMode::Manual);
+        respond(&fake, WmaxCommand::GetPowerMode, "0x77");
+        assert!(controller.current_power_mode().is_err());
+        respond(&fake, WmaxCommand::GetPowerMode, "Error: AE_NOT_FOUND");
+        assert!(controller.current_power_mode().is_err());
+    }
+
+    #[test]
+    fn read_all_sensors_parses_each_reading() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan1), "0xbb8");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan2), "0xffffffff");
+        respond(&fake, WmaxCommand::GetTemp(SensorId::Cpu), "0x41");
+        respond(
+            &fake,
+            WmaxCommand::GetTemp(SensorId::Gpu),
+            "Error: AE_NOT_FOUND",
+        );
+        respond(&fake, WmaxCommand::GetFanBoost(FanId::Fan1), "0x80");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        let readings = controller.read_all_sensors().unwrap();
+        assert_eq!(readings.fan1_rpm, Some(3000));
+        assert_eq!(readings.fan2_rpm, None);
+        assert_eq!(readings.cpu_temp, Some(65));
+        assert_eq!(readings.gpu_temp, None);
+        assert_eq!(readings.fan1_boost, Some(FanState::from_boost(128)));
+        assert_eq!(readings.fan2_boost, None);
+        assert!(readings.extra.is_empty());
+    }
+
+    #[test]
+    fn discovered_sensors_are_read_as_extra() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetTemp(SensorId::Cpu), "0x30");
+        respond(&fake, WmaxCommand::GetTemp(SensorId::Gpu), "0x28");
+        // Unpopulated thermistor
+        respond(&fake, WmaxCommand::GetTemp(SensorId::Other(0x05)), "0x0");
+        respond(&fake, WmaxCommand::GetTemp(SensorId::Other(0x0a)), "0x25");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan1), "0x0");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan2), "0x5dc");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        let found = controller.discover_sensors().unwrap().to_vec();
+        assert_eq!(
+            found,
+            [
+                WmaxSensor::Temp(SensorId::Cpu),
+                WmaxSensor::Temp(SensorId::Gpu),
+                WmaxSensor::Temp(SensorId::Other(0x0a)),
+                WmaxSensor::Fan(FanId::Fan1),
+                WmaxSensor::Fan(FanId::Fan2),
+            ]
+        );
+
+        let readings = controller.read_all_sensors().unwrap();
+        assert_eq!(readings.extra.len(), 1);
+        assert_eq!(readings.extra[0].id, "wmax:temp_0x0a");
+        assert_eq!(readings.extra[0].value, 37.0);
+    }
+
+    #[test]
+    fn extra_fans_need_to_be_spinning() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan1), "0x0");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan2), "0x0");
+        // Unpopulated fan ID answering 0 instead of 0xffffffff
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Extra(0x34)), "0x0");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Extra(0x35)), "0x4b0");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Extra(0x36)), "0xffffffff");
+        let mut controller = controller_for(&fake, "Something Else", "GenuineIntel");
+
+        let found = controller.discover_sensors().unwrap().to_vec();
+        assert_eq!(
+            found,
+            [
+                WmaxSensor::Fan(FanId::Fan1),
+                WmaxSensor::Fan(FanId::Fan2),
+                WmaxSensor::Fan(FanId::Extra(0x35)),
+            ]
+        );
+
+        let readings = controller.read_all_sensors().unwrap();
+        assert_eq!(readings.extra.len(), 1);
+        assert_eq!(readings.extra[0].id, "wmax:fan_0x35");
+        assert_eq!(readings.extra[0].value, 1200.0);
+    }
+
+    #[test]
+    fn known_models_only_keep_their_fans() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan1), "0x5dc");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Fan2), "0x5dc");
+        respond(&fake, WmaxCommand::GetFanRpm(FanId::Extra(0x34)), "0x5dc");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+        assert_eq!(controller.model.fans, 2);
+
+        let found = controller.discover_sensors().unwrap().to_vec();
+        assert_eq!(
+            found,
+            [WmaxSensor::Fan(FanId::Fan1), WmaxSensor::Fan(FanId::Fan2)]
+        );
+    }
+
+    #[test]
+    fn failed_call_is_an_error() {
+        let fake = FakeAcpiBackend::new();
+        let cmd = WmaxCommand::SetFanBoost(FanId::Fan1, 0xff);
+        fake.fail(cmd.method(), cmd.args(), "Not authorized");
+        let mut controller = controller_for(&fake, "Dell G15 5520", "GenuineIntel");
+
+        assert!(controller.set_fan_boost(FanId::Fan1, 0xff).is_err());
+        assert!(controller.set_fan_boost(FanId::Fan2, 0xff).is_ok());
     }
 }
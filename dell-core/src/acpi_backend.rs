@@ -0,0 +1,417 @@
+// Backends that execute WMAX methods for AcpiController (see also sysfs_backend)
+// The proc backend writes /proc/acpi/call directly when the process is
+// privileged, the pkexec one goes through polkit otherwise; the fake one
+// replays scripted responses so ACPI logic can be exercised without hardware
+
+use crate::acpi::{AMD_ACPI_PATH, INTEL_ACPI_PATH};
+use crate::power_mode::PowerMode;
+use crate::sysfs_backend::{SysfsBackend, SYSFS_ROOT};
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+pub const ACPI_CALL_PATH: &str = "/proc/acpi/call";
+
+/// acpi_call keeps a single result buffer for the whole system, so a write
+/// and the read of its result must not interleave with another call. Every
+/// process that touches /proc/acpi/call (the app, the exit watchdog, the
+/// helper) holds an flock on this file around each write+read pair.
+pub const ACPI_CALL_LOCK_PATH: &str = "/run/lock/dell-acpi-call.lock";
+
+// Serializes pkexec calls within the app, so concurrent callers don't
+// stack up authorization dialogs
+static PKEXEC_LOCK: Mutex<()> = Mutex::new(());
+
+/// Open and flock `ACPI_CALL_LOCK_PATH`; the lock lasts until the returned
+/// file is dropped. An existing file is opened read-only, since it may
+/// belong to another user (flock doesn't need write access).
+pub fn lock_acpi_call() -> io::Result<File> {
+    let file = match File::open(ACPI_CALL_LOCK_PATH) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(ACPI_CALL_LOCK_PATH)?,
+        other => other?,
+    };
+    file.lock()?;
+    Ok(file)
+}
+
+pub trait AcpiBackend: Send {
+    /// Call WMAX `method` on the object at `path` with a 4-byte argument
+    /// buffer and return the raw acpi_call result
+    fn call(&mut self, path: &str, method: u8, args: [u8; 4]) -> Result<String>;
+
+    /// Run several calls on `path` and return their raw results in order.
+    /// Backends that pay per invocation should override this to run the
+    /// whole batch at once.
+    fn call_batch(&mut self, path: &str, calls: &[(u8, [u8; 4])]) -> Result<Vec<String>> {
+        calls
+            .iter()
+            .map(|(method, args)| self.call(path, *method, *args))
+            .collect()
+    }
+
+    /// Whether `mode` can be set through this backend. WMAX accepts every
+    /// mode the model lists; backends that map modes onto something
+    /// narrower (sysfs) override this.
+    fn supports_mode(&self, _mode: PowerMode) -> bool {
+        true
+    }
+}
+
+/// acpi_call request line, e.g. `\_SB.AMWW.WMAX 0 0x15 {0x01, 0xa0, 0x00, 0x00}`
+pub fn format_call(path: &str, method: u8, args: [u8; 4]) -> String {
+    format!(
+        "{} 0 0x{:02x} {{0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}}}",
+        path, method, args[0], args[1], args[2], args[3]
+    )
+}
+
+/// Performs write-then-read on /proc/acpi/call itself. Only usable when
+/// the process may write the file (root, or a service with CAP_SYS_ADMIN).
+#[derive(Debug)]
+pub struct ProcAcpiBackend;
+
+impl ProcAcpiBackend {
+    /// `None` when /proc/acpi/call is missing or not writable by us
+    pub fn open() -> Option<Self> {
+        match OpenOptions::new().write(true).open(ACPI_CALL_PATH) {
+            Ok(_) => Some(Self),
+            Err(e) => {
+                debug!("{} not directly accessible: {}", ACPI_CALL_PATH, e);
+                None
+            }
+        }
+    }
+}
+
+impl AcpiBackend for ProcAcpiBackend {
+    fn call(&mut self, path: &str, method: u8, args: [u8; 4]) -> Result<String> {
+        let request = format_call(path, method, args);
+        let _lock = lock_acpi_call()
+            .map_err(|e| anyhow!("Failed to lock {}: {}", ACPI_CALL_LOCK_PATH, e))?;
+
+        OpenOptions::new()
+            .write(true)
+            .open(ACPI_CALL_PATH)
+            .and_then(|mut f| f.write_all(request.as_bytes()))
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to write '{}' to {}: {}",
+                    request,
+                    ACPI_CALL_PATH,
+                    e
+                )
+            })?;
+
+        let mut result = String::new();
+        OpenOptions::new()
+            .read(true)
+            .open(ACPI_CALL_PATH)
+            .and_then(|mut f| f.read_to_string(&mut result))
+            .map_err(|e| anyhow!("Failed to read {}: {}", ACPI_CALL_PATH, e))?;
+
+        debug!("ACPI result [{}]: {}", request, result.trim());
+        Ok(result.trim().to_string())
+    }
+}
+
+/// The kernel's alienware-wmi interface when loaded, then direct
+/// /proc/acpi/call access when privileged, the installed helper otherwise,
+/// and a pkexec shell as the last resort (development builds)
+pub fn default_backend() -> Box<dyn AcpiBackend> {
+    if let Some(backend) = SysfsBackend::detect(Path::new(SYSFS_ROOT)) {
+        info!("Using alienware-wmi sysfs interface");
+        return Box::new(backend);
+    }
+    if let Some(backend) = ProcAcpiBackend::open() {
+        info!("Using direct {} access", ACPI_CALL_PATH);
+        return Box::new(backend);
+    }
+    if let Some(backend) = HelperBackend::installed() {
+        info!("Using ACPI helper {}", HELPER_PATH);
+        return Box::new(backend);
+    }
+    warn!(
+        "{} not installed, falling back to pkexec sh (run setup-acpi.sh)",
+        HELPER_PATH
+    );
+    Box::new(PkexecBackend::new())
+}
+
+// Runs `pkexec <args>` and returns stdout, mapping a dismissed or denied
+// authorization to a readable error
+fn run_pkexec(args: &[&str], description: &str) -> Result<String> {
+    debug!("ACPI command: pkexec {:?}", args);
+
+    let _guard = PKEXEC_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let output = Command::new("pkexec").args(args).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("dismissed") || stderr.contains("Not authorized") {
+            error!("Authorization cancelled by the user for: {}", description);
+            return Err(anyhow!(
+                "Authorization cancelled; accept the authorization prompt to continue"
+            ));
+        }
+        error!("ACPI call '{}' failed: {}", description, stderr);
+        return Err(anyhow!(
+            "ACPI command '{}' failed: {}",
+            description,
+            stderr
+        ));
+    }
+
+    let result = String::from_utf8_lossy(&output.stdout).into_owned();
+    debug!("ACPI result [{}]: {}", description, result.trim());
+    Ok(result)
+}
+
+/// Where setup-acpi.sh installs the privileged helper
+pub const HELPER_PATH: &str = "/usr/bin/dell-acpi-helper";
+
+/// Runs allowlisted WMAX calls through `pkexec dell-acpi-helper`, which has
+/// its own polkit action instead of needing a rule for `sh`
+#[derive(Debug)]
+pub struct HelperBackend {
+    helper: String,
+}
+
+impl HelperBackend {
+    pub fn new(helper: &str) -> Self {
+        Self {
+            helper: helper.to_string(),
+        }
+    }
+
+    /// `None` when the helper isn't installed at `HELPER_PATH`
+    pub fn installed() -> Option<Self> {
+        Path::new(HELPER_PATH)
+            .is_file()
+            .then(|| Self::new(HELPER_PATH))
+    }
+
+    fn target(path: &str) -> Result<&'static str> {
+        match path {
+            INTEL_ACPI_PATH => Ok("intel"),
+            AMD_ACPI_PATH => Ok("amd"),
+            _ => Err(anyhow!("ACPI path not supported by the helper: {}", path)),
+        }
+    }
+}
+
+impl AcpiBackend for HelperBackend {
+    fn call(&mut self, path: &str, method: u8, args: [u8; 4]) -> Result<String> {
+        let mut results = self.call_batch(path, &[(method, args)])?;
+        Ok(results.remove(0))
+    }
+
+    fn call_batch(&mut self, path: &str, calls: &[(u8, [u8; 4])]) -> Result<Vec<String>> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let commands: Vec<String> = calls
+            .iter()
+            .map(|(method, a)| {
+                format!(
+                    "0x{:02x}:0x{:02x},0x{:02x},0x{:02x},0x{:02x}",
+                    method, a[0], a[1], a[2], a[3]
+                )
+            })
+            .collect();
+        let mut argv = vec![self.helper.as_str(), Self::target(path)?];
+        argv.extend(commands.iter().map(String::as_str));
+
+        let description = format!("{} {}", path, commands.join(" "));
+        let output = run_pkexec(&argv, &description)?;
+
+        // One line per command, in order
+        let results: Vec<String> = output.lines().map(|l| l.trim().to_string()).collect();
+        if results.len() != calls.len() {
+            return Err(anyhow!(
+                "Incomplete response from the ACPI helper: {} of {} results",
+                results.len(),
+                calls.len()
+            ));
+        }
+        Ok(results)
+    }
+}
+
+/// Runs calls as `pkexec sh -c "echo ... > /proc/acpi/call; cat /proc/acpi/call"`.
+/// A batch is chained into one script, so it costs a single pkexec.
+/// Needs a polkit rule for `sh`; only used when the helper isn't installed.
+#[derive(Debug, Default)]
+pub struct PkexecBackend;
+
+// Printed between results of a batch; acpi_call output never contains it
+const BATCH_SEPARATOR: &str = "--acpi-call-end--";
+
+impl PkexecBackend {
+    pub fn new() -> Self {
+        Self
+    }
+
+    // The script holds the shared acpi_call lock (flock(1) on fd 9) until
+    // it exits
+    fn run(&self, script: &str, description: &str) -> Result<String> {
+        let script = format!("exec 9>>{}; flock 9; {}", ACPI_CALL_LOCK_PATH, script);
+        run_pkexec(&["sh", "-c", &script], description)
+    }
+}
+
+impl AcpiBackend for PkexecBackend {
+    fn call(&mut self, path: &str, method: u8, args: [u8; 4]) -> Result<String> {
+        let request = format_call(path, method, args);
+        let result = self.run(
+            &format!(
+                "echo \"{}\" > /proc/acpi/call; cat /proc/acpi/call",
+                request
+            ),
+            &request,
+        )?;
+
+        // Parsing (quotes, NULs, error strings) is left to AcpiValue
+        result
+            .lines()
+            .last()
+            .map(|line| line.trim().to_string())
+            .ok_or_else(|| anyhow!("No response to ACPI command '{}'", request))
+    }
+
+    fn call_batch(&mut self, path: &str, calls: &[(u8, [u8; 4])]) -> Result<Vec<String>> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let script = calls
+            .iter()
+            .map(|(method, args)| {
+                format!(
+                    "echo \"{}\" > /proc/acpi/call; cat /proc/acpi/call; echo; echo {}",
+                    format_call(path, *method, *args),
+                    BATCH_SEPARATOR
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let description = format!("batch of {} calls on {}", calls.len(), path);
+        let output = self.run(&script, &description)?;
+
+        let results: Vec<String> = output
+            .split(BATCH_SEPARATOR)
+            .take(calls.len())
+            .map(|chunk| {
+                chunk
+                    .lines()
+                    .map(str::trim)
+                    .rfind(|line| !line.is_empty())
+                    .unwrap_or("")
+                    .to_string()
+            })
+            .collect();
+
+        if results.len() != calls.len() {
+            return Err(anyhow!(
+                "Incomplete response from {}: {} of {} results",
+                description,
+                results.len(),
+                calls.len()
+            ));
+        }
+        Ok(results)
+    }
+}
+
+/// A call made against the fake backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedCall {
+    pub path: String,
+    pub method: u8,
+    pub args: [u8; 4],
+}
+
+type CallKey = (Option<String>, u8, [u8; 4]);
+
+/// In-memory backend with responses scripted per method/arguments.
+/// Clones share the same script and call log, so a test can keep one
+/// handle after moving the other into an `AcpiController`.
+#[derive(Debug, Clone, Default)]
+pub struct FakeAcpiBackend {
+    responses: Arc<Mutex<HashMap<CallKey, Result<String, String>>>>,
+    calls: Arc<Mutex<Vec<RecordedCall>>>,
+}
+
+impl FakeAcpiBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Respond to `method`/`args` on any ACPI path
+    pub fn respond(&self, method: u8, args: [u8; 4], result: &str) -> &Self {
+        self.script(None, method, args, Ok(result.to_string()))
+    }
+
+    /// Respond to `method`/`args` only on `path` (takes precedence over `respond`)
+    pub fn respond_at(&self, path: &str, method: u8, args: [u8; 4], result: &str) -> &Self {
+        self.script(Some(path.to_string()), method, args, Ok(result.to_string()))
+    }
+
+    /// Make `method`/`args` fail as if the privileged call was rejected
+    pub fn fail(&self, method: u8, args: [u8; 4], message: &str) -> &Self {
+        self.script(None, method, args, Err(message.to_string()))
+    }
+
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    fn script(
+        &self,
+        path: Option<String>,
+        method: u8,
+        args: [u8; 4],
+        result: Result<String, String>,
+    ) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert((path, method, args), result);
+        self
+    }
+}
+
+impl AcpiBackend for FakeAcpiBackend {
+    fn call(&mut self, path: &str, method: u8, args: [u8; 4]) -> Result<String> {
+        self.calls.lock().unwrap().push(RecordedCall {
+            path: path.to_string(),
+            method,
+            args,
+        });
+
+        let responses = self.responses.lock().unwrap();
+        let response = responses
+            .get(&(Some(path.to_string()), method, args))
+            .or_else(|| responses.get(&(None, method, args)));
+
+        match response {
+            Some(Ok(result)) => Ok(result.clone()),
+            Some(Err(message)) => Err(anyhow!("{}", message)),
+            // What acpi_call reports for a method the firmware doesn't have
+            None => Ok("Error: AE_NOT_FOUND".to_string()),
+        }
+    }
+}
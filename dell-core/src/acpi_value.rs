@@ -0,0 +1,195 @@
+// Parsing of /proc/acpi/call results
+//
+// acpi_call answers with one of:
+//   0x12c0                  integer
+//   {0x01, 0x02, 0x03}      buffer
+//   "text"                  string
+//   Error: AE_NOT_FOUND     the firmware rejected the call
+//   not called              nothing was executed (e.g. bad request line)
+
+use thiserror::Error;
+
+/// Integer the WMAX getters return for a sensor/fan that isn't present
+const UNAVAILABLE: u64 = 0xffff_ffff;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcpiValue {
+    Integer(u64),
+    Buffer(Vec<u8>),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AcpiCallError {
+    #[error("ACPI call was not executed")]
+    NotCalled,
+    #[error("ACPI error: {0}")]
+    Firmware(String),
+    #[error("Unrecognized ACPI result: '{0}'")]
+    Malformed(String),
+}
+
+impl AcpiValue {
+    pub fn parse(raw: &str) -> Result<Self, AcpiCallError> {
+        // Output may end with a NUL and, when read by `cat` without a
+        // trailing newline, a `%` added by some shells
+        let value = raw
+            .trim()
+            .trim_end_matches('%')
+            .trim_end_matches('\0')
+            .trim();
+
+        if value.is_empty() || value == "not called" {
+            return Err(AcpiCallError::NotCalled);
+        }
+        if let Some(error) = value.strip_prefix("Error:") {
+            return Err(AcpiCallError::Firmware(error.trim().to_string()));
+        }
+
+        let malformed = || AcpiCallError::Malformed(value.to_string());
+
+        if let Some(inner) = value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+            return inner
+                .split(',')
+                .map(str::trim)
+                .filter(|b| !b.is_empty())
+                .map(|b| parse_integer(b).and_then(|v| u8::try_from(v).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .map(Self::Buffer)
+                .ok_or_else(malformed);
+        }
+
+        for quote in ['"', '\''] {
+            if let Some(inner) = value
+                .strip_prefix(quote)
+                .and_then(|v| v.strip_suffix(quote))
+            {
+                return Ok(Self::String(inner.trim_end_matches('\0').to_string()));
+            }
+        }
+
+        parse_integer(value)
+            .map(Self::Integer)
+            .ok_or_else(malformed)
+    }
+
+    pub fn as_integer(&self) -> Option<u64> {
+        match self {
+            Self::Integer(v) => Some(*v),
+            // Small buffers are little-endian integers
+            Self::Buffer(bytes) if !bytes.is_empty() && bytes.len() <= 8 => Some(
+                bytes
+                    .iter()
+                    .rev()
+                    .fold(0u64, |acc, b| (acc << 8) | *b as u64),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Integer reading, `None` when the firmware reports the sensor as absent
+    pub fn reading(&self) -> Result<Option<u32>, AcpiCallError> {
+        match self.as_integer() {
+            Some(UNAVAILABLE) => Ok(None),
+            Some(v) => u32::try_from(v)
+                .map(Some)
+                .map_err(|_| AcpiCallError::Malformed(format!("0x{:x}", v))),
+            None => Err(AcpiCallError::Malformed(format!("{:?}", self))),
+        }
+    }
+}
+
+fn parse_integer(value: &str) -> Option<u64> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(AcpiValue::parse("0x12c0"), Ok(AcpiValue::Integer(0x12c0)));
+        assert_eq!(AcpiValue::parse("0XFF"), Ok(AcpiValue::Integer(0xff)));
+        assert_eq!(AcpiValue::parse("42"), Ok(AcpiValue::Integer(42)));
+        assert_eq!(AcpiValue::parse("0"), Ok(AcpiValue::Integer(0)));
+    }
+
+    #[test]
+    fn trailing_nul_newline_and_percent() {
+        for raw in ["0xa1\0", "0xa1\n", "0xa1\0\n", "  0xa1%", "0xa1\0%\n"] {
+            assert_eq!(
+                AcpiValue::parse(raw),
+                Ok(AcpiValue::Integer(0xa1)),
+                "{:?}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn buffers_and_strings() {
+        assert_eq!(
+            AcpiValue::parse("{0x01, 0x02, 0xff}"),
+            Ok(AcpiValue::Buffer(vec![1, 2, 0xff]))
+        );
+        assert_eq!(AcpiValue::parse("{}"), Ok(AcpiValue::Buffer(Vec::new())));
+        assert_eq!(
+            AcpiValue::parse("\"G15\0\""),
+            Ok(AcpiValue::String("G15".to_string()))
+        );
+        assert!(matches!(
+            AcpiValue::parse("{0x100}"),
+            Err(AcpiCallError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn error_states() {
+        assert_eq!(
+            AcpiValue::parse("Error: AE_NOT_FOUND"),
+            Err(AcpiCallError::Firmware("AE_NOT_FOUND".to_string()))
+        );
+        assert_eq!(
+            AcpiValue::parse("Error: AE_AML_BUFFER_LIMIT\0"),
+            Err(AcpiCallError::Firmware("AE_AML_BUFFER_LIMIT".to_string()))
+        );
+        assert_eq!(
+            AcpiValue::parse("not called"),
+            Err(AcpiCallError::NotCalled)
+        );
+        assert_eq!(AcpiValue::parse(""), Err(AcpiCallError::NotCalled));
+        assert_eq!(AcpiValue::parse("\0\n"), Err(AcpiCallError::NotCalled));
+        assert!(matches!(
+            AcpiValue::parse("0xzz"),
+            Err(AcpiCallError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn unavailable_reading_is_none() {
+        let value = AcpiValue::parse("0xffffffff").unwrap();
+        assert_eq!(value.reading(), Ok(None));
+        assert_eq!(AcpiValue::Integer(0).reading(), Ok(Some(0)));
+        assert_eq!(AcpiValue::Integer(3000).reading(), Ok(Some(3000)));
+        // Larger than any reading, but not the "absent" marker
+        assert!(AcpiValue::Integer(0x1_0000_0000).reading().is_err());
+        assert!(AcpiValue::String("x".to_string()).reading().is_err());
+    }
+
+    #[test]
+    fn small_buffers_are_little_endian_integers() {
+        assert_eq!(
+            AcpiValue::Buffer(vec![0xc0, 0x12]).as_integer(),
+            Some(0x12c0)
+        );
+        assert_eq!(AcpiValue::Buffer(Vec::new()).as_integer(), None);
+        assert_eq!(AcpiValue::Buffer(vec![0; 9]).as_integer(), None);
+    }
+}
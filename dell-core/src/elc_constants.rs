@@ -59,3 +59,6 @@ pub const TEMPO_MIN: u16 = 0x01;
 pub const ZONES_ALL: [u8; 4] = [0, 1, 2, 3];
 pub const ZONES_KB: [u8; 3] = [0, 1, 2];
 pub const ZONES_NP: [u8; 1] = [3];
+/// Highest zone count an ELC device is driven with (keyboard plus logo,
+/// lightbar and other Alienware zones)
+pub const MAX_ZONES: u8 = 16;
@@ -0,0 +1,569 @@
+// Fan calibration and self-test
+// Sweeps both fans through a series of boost values, waits for the RPM to
+// settle at each step and records the boost -> RPM map. The map shows what
+// a boost value means on this machine; the sweep also catches fans that
+// don't spin, don't react to boost or run far apart from each other.
+
+use crate::acpi::AcpiController;
+use crate::config;
+use crate::hardware::HardwareInfo;
+use crate::models::LaptopModel;
+use crate::power_mode::PowerMode;
+use crate::wmax::FanId;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CALIBRATION_DIR: &str = "calibration";
+
+/// Below this a fan counts as stopped
+const SPIN_RPM: u32 = 300;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationSettings {
+    /// Boost values to visit, in order
+    pub steps: Vec<u8>,
+    /// Time between RPM samples
+    pub sample_interval_ms: u64,
+    /// Two samples this close count as settled
+    pub settle_tolerance_rpm: u32,
+    /// Give up waiting for a step to settle after this long
+    pub settle_timeout_ms: u64,
+    /// Largest accepted difference between the fans at full boost, as a
+    /// fraction of the faster one
+    pub max_asymmetry: f64,
+}
+
+impl Default for CalibrationSettings {
+    fn default() -> Self {
+        Self {
+            steps: vec![0, 64, 128, 192, 255],
+            sample_interval_ms: 1000,
+            settle_tolerance_rpm: 50,
+            settle_timeout_ms: 15000,
+            max_asymmetry: 0.3,
+        }
+    }
+}
+
+impl CalibrationSettings {
+    /// The anomaly checks compare the lowest and highest steps, so there
+    /// have to be at least two, in ascending order
+    pub fn validate(&self) -> Result<()> {
+        if self.steps.len() < 2 {
+            return Err(anyhow!("Calibration needs at least 2 steps"));
+        }
+        if self.steps.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(anyhow!(
+                "Calibration steps must be strictly increasing"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub boost: u8,
+    /// `None` if the fan reported no speed
+    pub rpm: Option<u32>,
+    /// The RPM stopped changing before the timeout
+    pub settled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FanCalibration {
+    pub fan: String,
+    pub points: Vec<CalibrationPoint>,
+}
+
+impl FanCalibration {
+    /// Lowest calibrated boost that gets the fan spinning
+    pub fn min_spin_boost(&self) -> Option<u8> {
+        self.points
+            .iter()
+            .find(|p| p.rpm.is_some_and(|r| r >= SPIN_RPM))
+            .map(|p| p.boost)
+    }
+
+    pub fn max_rpm(&self) -> Option<u32> {
+        self.points.iter().filter_map(|p| p.rpm).max()
+    }
+
+    /// Boost expected to give `rpm`, interpolated between calibrated points
+    pub fn boost_for_rpm(&self, rpm: u32) -> Option<u8> {
+        let points: Vec<(u8, u32)> = self
+            .points
+            .iter()
+            .filter_map(|p| Some((p.boost, p.rpm?)))
+            .collect();
+        let upper = points.iter().position(|&(_, r)| r >= rpm)?;
+        if upper == 0 {
+            return Some(points[0].0);
+        }
+        let ((b0, r0), (b1, r1)) = (points[upper - 1], points[upper]);
+        if r1 == r0 {
+            return Some(b1);
+        }
+        let t = (rpm - r0) as f64 / (r1 - r0) as f64;
+        Some((b0 as f64 + (b1 as f64 - b0 as f64) * t).round() as u8)
+    }
+
+    fn rpm_at(&self, boost: u8) -> Option<u32> {
+        self.points.iter().find(|p| p.boost == boost)?.rpm
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Anomaly {
+    /// No RPM reading at any step
+    NoReading { fan: String },
+    /// Still stopped at the highest boost
+    NotSpinning { fan: String, boost: u8 },
+    /// RPM at the highest boost is barely above the lowest one
+    NotResponding {
+        fan: String,
+        low_rpm: u32,
+        high_rpm: u32,
+    },
+    /// The fans run far apart at the same boost
+    Asymmetry {
+        boost: u8,
+        fan1_rpm: u32,
+        fan2_rpm: u32,
+    },
+}
+
+impl Anomaly {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NoReading { fan } => format!("{}: no RPM reading", fan),
+            Self::NotSpinning { fan, boost } => {
+                format!("{}: not spinning at boost {}", fan, boost)
+            }
+            Self::NotResponding {
+                fan,
+                low_rpm,
+                high_rpm,
+            } => format!(
+                "{}: doesn't respond to boost ({} -> {} RPM)",
+                fan, low_rpm, high_rpm
+            ),
+            Self::Asymmetry {
+                boost,
+                fan1_rpm,
+                fan2_rpm,
+            } => format!(
+                "Fans unbalanced at boost {}: {} vs {} RPM",
+                boost, fan1_rpm, fan2_rpm
+            ),
+        }
+    }
+}
+
+/// Where a running calibration is, for progress display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationProgress {
+    pub step: usize,
+    pub steps: usize,
+    pub boost: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    /// Key the report is stored under, see `machine_key`
+    pub machine: String,
+    pub model: String,
+    pub hardware: HardwareInfo,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    pub fans: Vec<FanCalibration>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl CalibrationReport {
+    /// DMI SKU when there is one, otherwise the model id
+    pub fn machine_key(hardware: &HardwareInfo, model: &LaptopModel) -> String {
+        let key = if hardware.product_sku.trim().is_empty() {
+            model.id.as_str()
+        } else {
+            hardware.product_sku.trim()
+        };
+        key.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>()
+            .to_lowercase()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+
+    fn default_path(machine: &str) -> Result<PathBuf> {
+        Ok(config::config_file(CALIBRATION_DIR)?.join(format!("{}.json", machine)))
+    }
+
+    /// Last report saved for `machine`, if any
+    pub fn load_default(machine: &str) -> Result<Option<Self>> {
+        Self::load(&Self::default_path(machine)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        config::load_json(path)
+    }
+
+    pub fn save_default(&self) -> Result<()> {
+        self.save(&Self::default_path(&self.machine)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        config::save_json(path, self)
+    }
+
+    /// Plain-text report
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "Fan calibration - {}", self.model);
+        let _ = writeln!(
+            text,
+            "{} {} (SKU {}, BIOS {})",
+            self.hardware.sys_vendor,
+            self.hardware.product_name,
+            self.hardware.product_sku,
+            self.hardware.bios_version
+        );
+        let _ = writeln!(text);
+
+        let _ = write!(text, "{:>6}", "Boost");
+        for fan in &self.fans {
+            let _ = write!(text, " {:>12}", fan.fan);
+        }
+        let _ = writeln!(text);
+        let steps = self.fans.first().map_or(0, |f| f.points.len());
+        for i in 0..steps {
+            let _ = write!(text, "{:>6}", self.fans[0].points[i].boost);
+            for fan in &self.fans {
+                let cell = match fan.points.get(i) {
+                    Some(CalibrationPoint {
+                        rpm: Some(rpm),
+                        settled,
+                        ..
+                    }) => format!("{}{}", rpm, if *settled { "" } else { "*" }),
+                    _ => "-".to_string(),
+                };
+                let _ = write!(text, " {:>12}", cell);
+            }
+            let _ = writeln!(text);
+        }
+        if self.fans.iter().flat_map(|f| &f.points).any(|p| !p.settled) {
+            let _ = writeln!(text, "(* = RPM still changing at the end of the wait)");
+        }
+        let _ = writeln!(text);
+
+        for fan in &self.fans {
+            let _ = writeln!(
+                text,
+                "{}: spins from boost {}, up to {} RPM",
+                fan.fan,
+                fan.min_spin_boost()
+                    .map_or("-".to_string(), |b| b.to_string()),
+                fan.max_rpm().map_or("-".to_string(), |r| r.to_string())
+            );
+        }
+        let _ = writeln!(text);
+
+        if self.passed() {
+            let _ = writeln!(text, "Result: OK");
+        } else {
+            let _ = writeln!(text, "Result: {} issue(s)", self.anomalies.len());
+            for anomaly in &self.anomalies {
+                let _ = writeln!(text, "  - {}", anomaly.describe());
+            }
+        }
+        text
+    }
+}
+
+fn with_acpi<T>(
+    acpi: &Mutex<Option<AcpiController>>,
+    f: impl FnOnce(&mut AcpiController) -> Result<T>,
+) -> Result<T> {
+    let mut acpi = acpi.lock().unwrap();
+    let controller = acpi
+        .as_mut()
+        .ok_or_else(|| anyhow!("ACPI not available"))?;
+    f(controller)
+}
+
+/// Sample both fans until neither changes by more than the tolerance
+fn settle(
+    acpi: &Mutex<Option<AcpiController>>,
+    settings: &CalibrationSettings,
+) -> Result<[(Option<u32>, bool); 2]> {
+    let interval = Duration::from_millis(settings.sample_interval_ms);
+    let deadline = Instant::now() + Duration::from_millis(settings.settle_timeout_ms);
+    let close = |a: Option<u32>, b: Option<u32>| match (a, b) {
+        (Some(a), Some(b)) => a.abs_diff(b) <= settings.settle_tolerance_rpm,
+        (a, b) => a == b,
+    };
+
+    std::thread::sleep(interval);
+    let mut last = with_acpi(acpi, |c| c.read_fan_rpms())?;
+    loop {
+        std::thread::sleep(interval);
+        let now = with_acpi(acpi, |c| c.read_fan_rpms())?;
+        let settled = [close(last.0, now.0), close(last.1, now.1)];
+        if settled == [true, true] || Instant::now() >= deadline {
+            return Ok([(now.0, settled[0]), (now.1, settled[1])]);
+        }
+        last = now;
+    }
+}
+
+fn find_anomalies(fans: &[FanCalibration], settings: &CalibrationSettings) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    let (Some(&low), Some(&high)) = (settings.steps.iter().min(), settings.steps.iter().max())
+    else {
+        return anomalies;
+    };
+
+    for fan in fans {
+        let (low_rpm, high_rpm) = (fan.rpm_at(low), fan.rpm_at(high));
+        match (low_rpm, high_rpm) {
+            _ if fan.max_rpm().is_none() => anomalies.push(Anomaly::NoReading {
+                fan: fan.fan.clone(),
+            }),
+            (_, Some(high_rpm)) if high_rpm < SPIN_RPM => anomalies.push(Anomaly::NotSpinning {
+                fan: fan.fan.clone(),
+                boost: high,
+            }),
+            // Full boost should be well above the lowest step
+            (Some(low_rpm), Some(high_rpm)) if high_rpm < low_rpm + low_rpm / 5 + SPIN_RPM => {
+                anomalies.push(Anomaly::NotResponding {
+                    fan: fan.fan.clone(),
+                    low_rpm,
+                    high_rpm,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    if let [fan1, fan2, ..] = fans {
+        if let (Some(fan1_rpm), Some(fan2_rpm)) = (fan1.rpm_at(high), fan2.rpm_at(high)) {
+            let faster = fan1_rpm.max(fan2_rpm);
+            // A stopped fan is already reported as not spinning
+            if fan1_rpm.min(fan2_rpm) >= SPIN_RPM
+                && fan1_rpm.abs_diff(fan2_rpm) as f64 > faster as f64 * settings.max_asymmetry
+            {
+                anomalies.push(Anomaly::Asymmetry {
+                    boost: high,
+                    fan1_rpm,
+                    fan2_rpm,
+                });
+            }
+        }
+    }
+    anomalies
+}
+
+/// Sweep the CPU and GPU fans together through `settings.steps`. The fans
+/// are driven in Manual mode; the previous power mode and boosts are put
+/// back afterwards, even if the sweep fails. Takes the ACPI lock only for
+/// each call, so sensors can still be read while it runs.
+pub fn calibrate(
+    acpi: &Mutex<Option<AcpiController>>,
+    settings: &CalibrationSettings,
+    mut progress: impl FnMut(CalibrationProgress),
+) -> Result<CalibrationReport> {
+    settings.validate()?;
+    let fans = [FanId::Fan1, FanId::Fan2];
+
+    let (machine, model, hardware, previous_mode, previous_boost) = with_acpi(acpi, |c| {
+        let previous_boost = fans.map(|f| c.get_fan_boost(f).ok().map(|s| s.boost));
+        Ok((
+            CalibrationReport::machine_key(&c.hardware, &c.model),
+            c.model.name.clone(),
+            c.hardware.clone(),
+            c.current_power_mode().ok(),
+            previous_boost,
+        ))
+    })?;
+    info!("Starting fan calibration for {}", machine);
+    with_acpi(acpi, |c| c.set_power_mode(PowerMode::Manual))?;
+
+    let mut results = fans.map(|f| FanCalibration {
+        fan: f.name(),
+        points: Vec::new(),
+    });
+    let sweep = (|| -> Result<()> {
+        for (i, &boost) in settings.steps.iter().enumerate() {
+            progress(CalibrationProgress {
+                step: i + 1,
+                steps: settings.steps.len(),
+                boost,
+            });
+            with_acpi(acpi, |c| {
+                for fan in fans {
+                    c.set_fan_boost(fan, boost)?;
+                }
+                Ok(())
+            })?;
+            for (result, (rpm, settled)) in results.iter_mut().zip(settle(acpi, settings)?) {
+                result.points.push(CalibrationPoint {
+                    boost,
+                    rpm,
+                    settled,
+                });
+            }
+        }
+        Ok(())
+    })();
+
+    // Put the fans back the way they were
+    let restore = with_acpi(acpi, |c| {
+        for (fan, boost) in fans.into_iter().zip(previous_boost) {
+            if let Some(boost) = boost {
+                c.set_fan_boost(fan, boost)?;
+            }
+        }
+        if let Some(mode) = previous_mode.filter(|m| *m != PowerMode::Manual) {
+            c.set_power_mode(mode)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = restore {
+        warn!(
+            "Failed to restore the fans after calibration: {}",
+            e
+        );
+    }
+    sweep?;
+
+    let fans = results.to_vec();
+    let anomalies = find_anomalies(&fans, settings);
+    let report = CalibrationReport {
+        machine,
+        model,
+        hardware,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        fans,
+        anomalies,
+    };
+    info!(
+        "Fan calibration finished: {} anomalies",
+        report.anomalies.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fan(name: &str, rpms: &[Option<u32>]) -> FanCalibration {
+        let steps = CalibrationSettings::default().steps;
+        FanCalibration {
+            fan: name.to_string(),
+            points: steps
+                .iter()
+                .zip(rpms)
+                .map(|(&boost, &rpm)| CalibrationPoint {
+                    boost,
+                    rpm,
+                    settled: true,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn settings_need_two_ascending_steps() {
+        let with_steps = |steps: &[u8]| CalibrationSettings {
+            steps: steps.to_vec(),
+            ..CalibrationSettings::default()
+        };
+        assert!(CalibrationSettings::default().validate().is_ok());
+        assert!(with_steps(&[0, 255]).validate().is_ok());
+        for steps in [&[][..], &[128], &[255, 0], &[0, 128, 128, 255]] {
+            assert!(with_steps(steps).validate().is_err(), "{:?}", steps);
+        }
+    }
+
+    #[test]
+    fn healthy_fans_have_no_anomalies() {
+        let fans = [
+            fan(
+                "CPU Fan",
+                &[Some(0), Some(1800), Some(2900), Some(3900), Some(4800)],
+            ),
+            fan(
+                "GPU Fan",
+                &[Some(0), Some(1900), Some(3000), Some(4100), Some(5000)],
+            ),
+        ];
+        assert_eq!(find_anomalies(&fans, &CalibrationSettings::default()), []);
+        assert_eq!(fans[0].min_spin_boost(), Some(64));
+        assert_eq!(fans[0].boost_for_rpm(2350), Some(96));
+    }
+
+    #[test]
+    fn broken_fans_are_reported() {
+        let settings = CalibrationSettings::default();
+        let fans = [
+            fan("CPU Fan", &[Some(0), Some(0), Some(0), Some(0), Some(0)]),
+            fan("GPU Fan", &[None; 5]),
+        ];
+        assert_eq!(
+            find_anomalies(&fans, &settings),
+            [
+                Anomaly::NotSpinning {
+                    fan: "CPU Fan".to_string(),
+                    boost: 255,
+                },
+                Anomaly::NoReading {
+                    fan: "GPU Fan".to_string(),
+                },
+            ]
+        );
+
+        let fans = [
+            fan(
+                "CPU Fan",
+                &[Some(2000), Some(2000), Some(2100), Some(2100), Some(2200)],
+            ),
+            fan(
+                "GPU Fan",
+                &[Some(0), Some(1000), Some(2000), Some(3000), Some(4000)],
+            ),
+        ];
+        assert_eq!(
+            find_anomalies(&fans, &settings),
+            [
+                Anomaly::NotResponding {
+                    fan: "CPU Fan".to_string(),
+                    low_rpm: 2000,
+                    high_rpm: 2200,
+                },
+                Anomaly::Asymmetry {
+                    boost: 255,
+                    fan1_rpm: 2200,
+                    fan2_rpm: 4000,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+// Temperature-driven fan curves
+// A background thread reads the CPU and GPU temperatures, looks up each
+// fan's boost on its curve and programs it with set_fan_boost. Hysteresis,
+// ramp limiting and a minimum write interval keep the fans from hunting
+// and the EC from being flooded with writes.
+
+use crate::acpi::AcpiController;
+use crate::config;
+use crate::power_mode::PowerMode;
+use crate::wmax::FanId;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const FAN_CURVE_FILE: &str = "fan_curve.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    /// °C
+    pub temp: u32,
+    /// Raw boost (0-255)
+    pub boost: u8,
+}
+
+impl CurvePoint {
+    pub const fn new(temp: u32, boost: u8) -> Self {
+        Self { temp, boost }
+    }
+}
+
+/// Temperature a fan follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TempSource {
+    Cpu,
+    /// GPU temperature, or the CPU one while the GPU reports nothing
+    Gpu,
+    /// The hotter of CPU and GPU
+    Max,
+}
+
+impl TempSource {
+    pub fn pick(&self, cpu: Option<u32>, gpu: Option<u32>) -> Option<u32> {
+        match self {
+            Self::Cpu => cpu,
+            Self::Gpu => gpu.or(cpu),
+            Self::Max => match (cpu, gpu) {
+                (Some(cpu), Some(gpu)) => Some(cpu.max(gpu)),
+                (cpu, gpu) => cpu.or(gpu),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FanCurve {
+    pub source: TempSource,
+    /// Sorted by temperature; boost is interpolated linearly between
+    /// points and held flat outside them
+    pub points: Vec<CurvePoint>,
+}
+
+impl FanCurve {
+    pub fn new(source: TempSource, points: Vec<CurvePoint>) -> Self {
+        Self { source, points }
+    }
+
+    pub fn boost_at(&self, temp: u32) -> u8 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return 0;
+        };
+        if temp <= first.temp {
+            return first.boost;
+        }
+        if temp >= last.temp {
+            return last.boost;
+        }
+
+        let upper = self
+            .points
+            .iter()
+            .position(|p| p.temp >= temp)
+            .unwrap_or(self.points.len() - 1);
+        let (a, b) = (self.points[upper - 1], self.points[upper]);
+        let t = (temp - a.temp) as f64 / (b.temp - a.temp) as f64;
+        (a.boost as f64 + (b.boost as f64 - a.boost as f64) * t).round() as u8
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.points.is_empty() {
+            return Err(anyhow!("Fan curve has no points"));
+        }
+        if self.points.windows(2).any(|w| w[0].temp >= w[1].temp) {
+            return Err(anyhow!(
+                "Fan curve temperatures must be strictly increasing"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FanCurveConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub cpu_fan: FanCurve,
+    pub gpu_fan: FanCurve,
+    /// °C the temperature has to drop before the boost is lowered
+    pub hysteresis: u32,
+    /// Largest boost change per write
+    pub ramp_step: u8,
+    /// Shortest time between two writes to the same fan
+    pub min_write_interval_ms: u64,
+    pub poll_interval_ms: u64,
+}
+
+impl Default for FanCurveConfig {
+    fn default() -> Self {
+        let points = vec![
+            CurvePoint::new(45, 0),
+            CurvePoint::new(60, 64),
+            CurvePoint::new(70, 128),
+            CurvePoint::new(80, 192),
+            CurvePoint::new(90, 255),
+        ];
+        Self {
+            enabled: false,
+            cpu_fan: FanCurve::new(TempSource::Cpu, points.clone()),
+            gpu_fan: FanCurve::new(TempSource::Gpu, points),
+            hysteresis: 4,
+            ramp_step: 32,
+            min_write_interval_ms: 3000,
+            poll_interval_ms: 1000,
+        }
+    }
+}
+
+impl FanCurveConfig {
+    /// Config stored in the user's config directory, defaults if missing
+    pub fn load_default() -> Result<Self> {
+        Self::load(&config::config_file(FAN_CURVE_FILE)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let config: Self = config::load_json(path)?.unwrap_or_default();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn save_default(&self) -> Result<()> {
+        self.save(&config::config_file(FAN_CURVE_FILE)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        config::save_json(path, self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.cpu_fan.validate()?;
+        self.gpu_fan.validate()?;
+        if self.ramp_step == 0 {
+            return Err(anyhow!("ramp_step must be at least 1"));
+        }
+        if self.poll_interval_ms < 100 {
+            return Err(anyhow!("poll_interval_ms must be at least 100"));
+        }
+        Ok(())
+    }
+
+    pub fn curves(&self) -> [(FanId, &FanCurve); 2] {
+        [(FanId::Fan1, &self.cpu_fan), (FanId::Fan2, &self.gpu_fan)]
+    }
+}
+
+/// Per-fan state between polls
+#[derive(Debug, Default)]
+struct CurveTracker {
+    /// Temperature the current target was taken at
+    anchor: Option<u32>,
+    /// Last boost written
+    boost: Option<u8>,
+    last_write: Option<Instant>,
+}
+
+impl CurveTracker {
+    /// Boost to write now, if it should change
+    fn update(
+        &mut self,
+        curve: &FanCurve,
+        temp: u32,
+        config: &FanCurveConfig,
+        now: Instant,
+    ) -> Option<u8> {
+        // Rising temperatures are followed right away, falling ones only
+        // once they are `hysteresis` below the point the target came from
+        let anchor = match self.anchor {
+            Some(anchor) if temp < anchor && anchor - temp < config.hysteresis => anchor,
+            _ => temp,
+        };
+        self.anchor = Some(anchor);
+
+        let target = curve.boost_at(anchor);
+        let next = match self.boost {
+            Some(current) if current < target => {
+                current.saturating_add(config.ramp_step).min(target)
+            }
+            Some(current) => current.saturating_sub(config.ramp_step).max(target),
+            // Nothing written yet, so there is no known boost to ramp from
+            None => target,
+        };
+        if Some(next) == self.boost {
+            return None;
+        }
+
+        let min_interval = Duration::from_millis(config.min_write_interval_ms);
+        match self.last_write {
+            Some(last) if now.duration_since(last) < min_interval => None,
+            _ => Some(next),
+        }
+    }
+
+    fn written(&mut self, boost: u8, now: Instant) {
+        self.boost = Some(boost);
+        self.last_write = Some(now);
+    }
+}
+
+/// Background thread applying a `FanCurveConfig`; stops when dropped
+pub struct FanCurveController {
+    config: Arc<Mutex<FanCurveConfig>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FanCurveController {
+    /// Switch to Manual mode (the EC ignores boost otherwise) and start
+    /// following the curves
+    pub fn start(acpi: Arc<Mutex<Option<AcpiController>>>, config: FanCurveConfig) -> Self {
+        let config = Arc::new(Mutex::new(config));
+        let stop = Arc::new(AtomicBool::new(false));
+        let config_ref = config.clone();
+        let stop_flag = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            if let Some(controller) = acpi.lock().unwrap().as_mut() {
+                if let Err(e) = controller.set_power_mode(PowerMode::Manual) {
+                    warn!("Fan curve: failed to switch to Manual mode: {}", e);
+                }
+            }
+            info!("Fan curve controller started");
+
+            let mut trackers = [CurveTracker::default(), CurveTracker::default()];
+            while !stop_flag.load(Ordering::Relaxed) {
+                let config = config_ref.lock().unwrap().clone();
+                if let Err(e) = Self::tick(&acpi, &config, &mut trackers) {
+                    warn!("Fan curve update failed: {}", e);
+                }
+                std::thread::park_timeout(Duration::from_millis(config.poll_interval_ms));
+            }
+            debug!("Fan curve controller stopped");
+        });
+
+        Self {
+            config,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn tick(
+        acpi: &Mutex<Option<AcpiController>>,
+        config: &FanCurveConfig,
+        trackers: &mut [CurveTracker; 2],
+    ) -> Result<()> {
+        let mut acpi = acpi.lock().unwrap();
+        let Some(controller) = acpi.as_mut() else {
+            return Ok(());
+        };
+
+        let (cpu, gpu) = controller.read_temperatures()?;
+        let now = Instant::now();
+        for ((fan, curve), tracker) in config.curves().into_iter().zip(trackers.iter_mut()) {
+            let Some(temp) = curve.source.pick(cpu, gpu) else {
+                continue;
+            };
+            if let Some(boost) = tracker.update(curve, temp, config, now) {
+                debug!("Fan curve: {:?} at {}°C -> boost {}", fan, temp, boost);
+                // A failed write is retried next poll and doesn't hold up
+                // the other fan
+                match controller.set_fan_boost(fan, boost) {
+                    Ok(()) => tracker.written(boost, now),
+                    Err(e) => warn!("Fan curve: {}", e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn config(&self) -> FanCurveConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Replace the curves; applied from the next poll
+    pub fn set_config(&self, config: FanCurveConfig) -> Result<()> {
+        config.validate()?;
+        *self.config.lock().unwrap() = config;
+        Ok(())
+    }
+}
+
+impl Drop for FanCurveController {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 5 boost per °C between 40 and 80 °C
+    fn linear() -> FanCurve {
+        FanCurve::new(
+            TempSource::Cpu,
+            vec![CurvePoint::new(40, 0), CurvePoint::new(80, 200)],
+        )
+    }
+
+    fn config(ramp_step: u8, min_write_interval_ms: u64) -> FanCurveConfig {
+        FanCurveConfig {
+            hysteresis: 4,
+            ramp_step,
+            min_write_interval_ms,
+            ..FanCurveConfig::default()
+        }
+    }
+
+    /// Feed `temp` at `now` and record the write, like `tick` does
+    fn step(
+        tracker: &mut CurveTracker,
+        config: &FanCurveConfig,
+        temp: u32,
+        now: Instant,
+    ) -> Option<u8> {
+        let boost = tracker.update(&linear(), temp, config, now)?;
+        tracker.written(boost, now);
+        Some(boost)
+    }
+
+    #[test]
+    fn boost_is_interpolated_and_flat_outside_the_points() {
+        let curve = FanCurveConfig::default().cpu_fan;
+        assert_eq!(curve.boost_at(20), 0);
+        assert_eq!(curve.boost_at(45), 0);
+        assert_eq!(curve.boost_at(52), 30);
+        assert_eq!(curve.boost_at(60), 64);
+        assert_eq!(curve.boost_at(65), 96);
+        assert_eq!(curve.boost_at(90), 255);
+        assert_eq!(curve.boost_at(110), 255);
+
+        assert_eq!(linear().boost_at(41), 5);
+        assert_eq!(FanCurve::new(TempSource::Cpu, Vec::new()).boost_at(70), 0);
+    }
+
+    #[test]
+    fn gpu_source_falls_back_to_cpu() {
+        assert_eq!(TempSource::Gpu.pick(Some(70), Some(60)), Some(60));
+        assert_eq!(TempSource::Gpu.pick(Some(70), None), Some(70));
+        assert_eq!(TempSource::Gpu.pick(None, None), None);
+        assert_eq!(TempSource::Cpu.pick(None, Some(60)), None);
+        assert_eq!(TempSource::Max.pick(Some(70), Some(75)), Some(75));
+        assert_eq!(TempSource::Max.pick(None, Some(60)), Some(60));
+    }
+
+    #[test]
+    fn rising_temperature_is_followed_right_away() {
+        let config = config(255, 0);
+        let mut tracker = CurveTracker::default();
+        let now = Instant::now();
+        assert_eq!(step(&mut tracker, &config, 50, now), Some(50));
+        assert_eq!(step(&mut tracker, &config, 51, now), Some(55));
+        assert_eq!(step(&mut tracker, &config, 60, now), Some(100));
+        assert_eq!(step(&mut tracker, &config, 60, now), None);
+    }
+
+    #[test]
+    fn falling_temperature_waits_for_the_hysteresis() {
+        let config = config(255, 0);
+        let mut tracker = CurveTracker::default();
+        let now = Instant::now();
+        assert_eq!(step(&mut tracker, &config, 60, now), Some(100));
+        assert_eq!(step(&mut tracker, &config, 58, now), None);
+        assert_eq!(step(&mut tracker, &config, 57, now), None);
+        assert_eq!(step(&mut tracker, &config, 56, now), Some(80));
+        // The anchor moved down with it
+        assert_eq!(step(&mut tracker, &config, 54, now), None);
+        assert_eq!(step(&mut tracker, &config, 57, now), Some(85));
+    }
+
+    #[test]
+    fn changes_are_limited_to_ramp_step() {
+        let config = config(32, 0);
+        let mut tracker = CurveTracker::default();
+        let now = Instant::now();
+        // The first write has nothing to ramp from
+        assert_eq!(step(&mut tracker, &config, 40, now), Some(0));
+        assert_eq!(step(&mut tracker, &config, 80, now), Some(32));
+        assert_eq!(step(&mut tracker, &config, 80, now), Some(64));
+        for _ in 0..4 {
+            step(&mut tracker, &config, 80, now);
+        }
+        assert_eq!(step(&mut tracker, &config, 80, now), Some(200));
+        assert_eq!(step(&mut tracker, &config, 40, now), Some(168));
+    }
+
+    #[test]
+    fn writes_wait_for_the_minimum_interval() {
+        let config = config(255, 3000);
+        let mut tracker = CurveTracker::default();
+        let start = Instant::now();
+        assert_eq!(step(&mut tracker, &config, 50, start), Some(50));
+
+        let soon = start + Duration::from_millis(1000);
+        assert_eq!(step(&mut tracker, &config, 70, soon), None);
+        assert_eq!(tracker.boost, Some(50));
+
+        let later = start + Duration::from_millis(3000);
+        assert_eq!(step(&mut tracker, &config, 70, later), Some(150));
+    }
+
+    #[test]
+    fn failed_writes_are_retried() {
+        let config = config(255, 3000);
+        let mut tracker = CurveTracker::default();
+        let now = Instant::now();
+        // update without written: the write failed
+        assert_eq!(tracker.update(&linear(), 60, &config, now), Some(100));
+        assert_eq!(tracker.update(&linear(), 60, &config, now), Some(100));
+    }
+}
@@ -0,0 +1,336 @@
+// Multi-color gradients spread across the four keyboard zones
+// Colors are interpolated between stops in the selected color space
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    #[default]
+    Rgb,
+    Hsv,
+    Oklab,
+}
+
+impl ColorSpace {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "rgb" => Ok(Self::Rgb),
+            "hsv" => Ok(Self::Hsv),
+            "oklab" => Ok(Self::Oklab),
+            _ => Err(anyhow!("Unknown color space: {}", name)),
+        }
+    }
+}
+
+/// A gradient color at a position between 0.0 (zone 0) and 1.0 (zone 3)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    pub position: f32,
+    pub color: [u8; 3],
+}
+
+impl ColorStop {
+    pub fn new(position: f32, color: [u8; 3]) -> Self {
+        Self { position, color }
+    }
+}
+
+/// Deserialized through `Gradient::new`, so a gradient read from JSON or
+/// any other serde input gets the same validation as the API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "GradientData")]
+pub struct Gradient {
+    stops: Vec<ColorStop>,
+    #[serde(default)]
+    color_space: ColorSpace,
+}
+
+#[derive(Deserialize)]
+struct GradientData {
+    stops: Vec<ColorStop>,
+    #[serde(default)]
+    color_space: ColorSpace,
+}
+
+impl TryFrom<GradientData> for Gradient {
+    type Error = anyhow::Error;
+
+    fn try_from(data: GradientData) -> Result<Self> {
+        Self::new(&data.stops, data.color_space)
+    }
+}
+
+impl Gradient {
+    pub fn new(stops: &[ColorStop], color_space: ColorSpace) -> Result<Self> {
+        if stops.len() < 2 {
+            return Err(anyhow!("A gradient needs at least two color stops"));
+        }
+        if stops
+            .iter()
+            .any(|s| !s.position.is_finite() || !(0.0..=1.0).contains(&s.position))
+        {
+            return Err(anyhow!("Color stop positions must be between 0.0 and 1.0"));
+        }
+
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        Ok(Self { stops, color_space })
+    }
+
+    /// Evenly spaced stops from a list of colors
+    pub fn even(colors: &[[u8; 3]], color_space: ColorSpace) -> Result<Self> {
+        if colors.len() < 2 {
+            return Err(anyhow!("A gradient needs at least two color stops"));
+        }
+        let last = (colors.len() - 1) as f32;
+        let stops: Vec<ColorStop> = colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| ColorStop::new(i as f32 / last, *c))
+            .collect();
+        Self::new(&stops, color_space)
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Color at position `t` (clamped to 0.0..=1.0)
+    pub fn sample(&self, t: f32) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+
+        if t <= first.position {
+            return first.color;
+        }
+        if t >= last.position {
+            return last.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if t >= a.position && t <= b.position {
+                let span = b.position - a.position;
+                if span <= f32::EPSILON {
+                    return b.color;
+                }
+                let local = (t - a.position) / span;
+                return interpolate(a.color, b.color, local, self.color_space);
+            }
+        }
+
+        last.color
+    }
+
+    /// Colors for zones 0-3, left to right
+    pub fn zone_colors(&self) -> [[u8; 3]; 4] {
+        self.zone_colors_shifted(0.0)
+    }
+
+    /// Zone colors with the gradient scrolled by `offset` (wraps around and
+    /// mirrors so a looping animation has no visible seam)
+    pub fn zone_colors_shifted(&self, offset: f32) -> [[u8; 3]; 4] {
+        let mut colors = [[0u8; 3]; 4];
+        for (zone, color) in colors.iter_mut().enumerate() {
+            let t = (zone as f32 / 3.0 + offset).rem_euclid(2.0);
+            let t = if t > 1.0 { 2.0 - t } else { t };
+            *color = self.sample(t);
+        }
+        colors
+    }
+}
+
+fn interpolate(a: [u8; 3], b: [u8; 3], t: f32, space: ColorSpace) -> [u8; 3] {
+    match space {
+        ColorSpace::Rgb => {
+            let mix = |x: u8, y: u8| lerp(x as f32, y as f32, t).round() as u8;
+            [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+        }
+        ColorSpace::Hsv => {
+            let (h1, s1, v1) = rgb_to_hsv(a);
+            let (h2, s2, v2) = rgb_to_hsv(b);
+
+            // Take the short way around the hue circle
+            let mut dh = h2 - h1;
+            if dh > 180.0 {
+                dh -= 360.0;
+            } else if dh < -180.0 {
+                dh += 360.0;
+            }
+            let h = (h1 + dh * t).rem_euclid(360.0);
+            hsv_to_rgb(h, lerp(s1, s2, t), lerp(v1, v2, t))
+        }
+        ColorSpace::Oklab => {
+            let la = rgb_to_oklab(a);
+            let lb = rgb_to_oklab(b);
+            oklab_to_rgb([
+                lerp(la[0], lb[0], t),
+                lerp(la[1], lb[1], t),
+                lerp(la[2], lb[2], t),
+            ])
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn rgb_to_hsv(rgb: [u8; 3]) -> (f32, f32, f32) {
+    let r = rgb[0] as f32 / 255.0;
+    let g = rgb[1] as f32 / 255.0;
+    let b = rgb[2] as f32 / 255.0;
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+
+    (h, s, max)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [u8; 3] {
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = v - c;
+
+    let (r, g, b) = match h as u32 {
+        0..=59 => (c, x, 0.0),
+        60..=119 => (x, c, 0.0),
+        120..=179 => (0.0, c, x),
+        180..=239 => (0.0, x, c),
+        240..=299 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    [to_u8(r + m), to_u8(g + m), to_u8(b + m)]
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.max(0.0).powf(1.0 / 2.4) - 0.055
+    };
+    to_u8(c)
+}
+
+fn to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// Conversion matrices from https://bottosson.github.io/posts/oklab/
+fn rgb_to_oklab(rgb: [u8; 3]) -> [f32; 3] {
+    let r = srgb_to_linear(rgb[0]);
+    let g = srgb_to_linear(rgb[1]);
+    let b = srgb_to_linear(rgb[2]);
+
+    let l = (0.4122215 * r + 0.5363325 * g + 0.05144599 * b).cbrt();
+    let m = (0.2119035 * r + 0.6806995 * g + 0.107397 * b).cbrt();
+    let s = (0.08830246 * r + 0.2817188 * g + 0.6299787 * b).cbrt();
+
+    [
+        0.2104543 * l + 0.7936178 * m - 0.004072047 * s,
+        1.977998 * l - 2.428592 * m + 0.4505937 * s,
+        0.02590404 * l + 0.7827718 * m - 0.8086758 * s,
+    ]
+}
+
+fn oklab_to_rgb(lab: [f32; 3]) -> [u8; 3] {
+    let l = lab[0] + 0.3963378 * lab[1] + 0.2158038 * lab[2];
+    let m = lab[0] - 0.1055613 * lab[1] - 0.06385417 * lab[2];
+    let s = lab[0] - 0.08948418 * lab[1] - 1.291486 * lab[2];
+
+    let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+
+    [
+        linear_to_srgb(4.076742 * l - 3.307712 * m + 0.2309699 * s),
+        linear_to_srgb(-1.268438 * l + 2.609757 * m - 0.3413194 * s),
+        linear_to_srgb(-0.004196077 * l - 0.7034186 * m + 1.707615 * s),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_stops_spread_across_zones() {
+        let gradient = Gradient::even(&[[0, 0, 0], [255, 255, 255]], ColorSpace::Rgb).unwrap();
+        assert_eq!(
+            gradient.zone_colors(),
+            [[0, 0, 0], [85, 85, 85], [170, 170, 170], [255, 255, 255]]
+        );
+    }
+
+    #[test]
+    fn stops_are_sorted_by_position() {
+        let stops = [
+            ColorStop::new(1.0, [0, 0, 255]),
+            ColorStop::new(0.0, [255, 0, 0]),
+        ];
+        let gradient = Gradient::new(&stops, ColorSpace::Rgb).unwrap();
+        assert_eq!(gradient.sample(0.0), [255, 0, 0]);
+        assert_eq!(gradient.sample(1.0), [0, 0, 255]);
+    }
+
+    #[test]
+    fn invalid_stops_are_rejected() {
+        assert!(Gradient::new(&[ColorStop::new(0.0, [0, 0, 0])], ColorSpace::Rgb).is_err());
+        let out_of_range = [
+            ColorStop::new(0.0, [0, 0, 0]),
+            ColorStop::new(1.5, [0, 0, 0]),
+        ];
+        assert!(Gradient::new(&out_of_range, ColorSpace::Rgb).is_err());
+    }
+
+    #[test]
+    fn deserializing_validates_stops() {
+        let empty: Result<Gradient, _> = serde_json::from_str(r#"{"stops": []}"#);
+        assert!(empty.is_err());
+
+        let json = r#"{"stops": [
+            {"position": 1.0, "color": [0, 0, 255]},
+            {"position": 0.0, "color": [255, 0, 0]}
+        ], "color_space": "oklab"}"#;
+        let gradient: Gradient = serde_json::from_str(json).unwrap();
+        assert_eq!(gradient.color_space(), ColorSpace::Oklab);
+        assert_eq!(gradient.stops()[0].color, [255, 0, 0]);
+    }
+
+    #[test]
+    fn hsv_takes_the_short_way_around_the_hue_circle() {
+        // Red to magenta passes through pink, not green
+        let gradient = Gradient::even(&[[255, 0, 0], [255, 0, 255]], ColorSpace::Hsv).unwrap();
+        let middle = gradient.sample(0.5);
+        assert_eq!(middle[1], 0);
+        assert!(middle[0] == 255 && middle[2] > 0);
+    }
+}
@@ -0,0 +1,95 @@
+// Hardware identification from DMI and /proc/cpuinfo
+// Read straight from the filesystem (no root needed) under a configurable
+// root, so model matching can run against a fixture tree
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const DMI_DIR: &str = "sys/class/dmi/id";
+const CPUINFO: &str = "proc/cpuinfo";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareInfo {
+    pub sys_vendor: String,
+    pub product_name: String,
+    /// Dell's SKU ID, e.g. `0BD8`
+    pub product_sku: String,
+    pub board_name: String,
+    pub bios_version: String,
+    /// `vendor_id` from /proc/cpuinfo, e.g. `GenuineIntel` or `AuthenticAMD`
+    pub cpu_vendor: String,
+}
+
+impl HardwareInfo {
+    /// Read from the running system
+    pub fn detect() -> Self {
+        Self::read_from(Path::new("/"))
+    }
+
+    /// Read `<root>/sys/class/dmi/id/*` and `<root>/proc/cpuinfo`; missing
+    /// entries are left empty
+    pub fn read_from(root: &Path) -> Self {
+        let dmi = |name: &str| {
+            std::fs::read_to_string(root.join(DMI_DIR).join(name))
+                .map(|v| v.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let cpu_vendor = std::fs::read_to_string(root.join(CPUINFO))
+            .ok()
+            .and_then(|cpuinfo| {
+                cpuinfo.lines().find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    (key.trim() == "vendor_id").then(|| value.trim().to_string())
+                })
+            })
+            .unwrap_or_default();
+
+        Self {
+            sys_vendor: dmi("sys_vendor"),
+            product_name: dmi("product_name"),
+            product_sku: dmi("product_sku"),
+            board_name: dmi("board_name"),
+            bios_version: dmi("bios_version"),
+            cpu_vendor,
+        }
+    }
+
+    pub fn is_amd(&self) -> bool {
+        self.cpu_vendor == "AuthenticAMD"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn reads_dmi_and_cpu_vendor() {
+        let hardware = HardwareInfo::read_from(&fixture("dmi-g15-5530"));
+        assert_eq!(
+            hardware,
+            HardwareInfo {
+                sys_vendor: "Dell Inc.".to_string(),
+                product_name: "Dell G15 5530".to_string(),
+                product_sku: "0C05".to_string(),
+                board_name: "0TKYPW".to_string(),
+                bios_version: "1.12.0".to_string(),
+                cpu_vendor: "GenuineIntel".to_string(),
+            }
+        );
+        assert!(!hardware.is_amd());
+    }
+
+    #[test]
+    fn missing_entries_are_empty() {
+        let hardware = HardwareInfo::read_from(&fixture("no-such-root"));
+        assert_eq!(hardware, HardwareInfo::default());
+    }
+}
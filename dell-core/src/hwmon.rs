@@ -0,0 +1,137 @@
+// hwmon devices under <sysfs root>/class/hwmon
+// Thin attribute access used by the sysfs backend and sensor discovery
+
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HwmonDevice {
+    /// Driver name from the `name` attribute, e.g. `coretemp`
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl HwmonDevice {
+    /// Every hwmon device, sorted by path so hwmon0 comes first
+    pub fn all(sysfs_root: &Path) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(sysfs_root.join("class/hwmon")) else {
+            return Vec::new();
+        };
+
+        let mut devices: Vec<Self> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = std::fs::read_to_string(path.join("name")).ok()?;
+                Some(Self {
+                    name: name.trim().to_string(),
+                    path,
+                })
+            })
+            .collect();
+        devices.sort_by(|a, b| a.path.cmp(&b.path));
+        devices
+    }
+
+    pub fn find(sysfs_root: &Path, name: &str) -> Option<Self> {
+        Self::all(sysfs_root).into_iter().find(|d| d.name == name)
+    }
+
+    pub fn read_attr(&self, attr: &str) -> Option<String> {
+        std::fs::read_to_string(self.path.join(attr))
+            .ok()
+            .map(|v| v.trim().to_string())
+    }
+
+    pub fn read_value(&self, attr: &str) -> Option<i64> {
+        self.read_attr(attr)?.parse().ok()
+    }
+
+    pub fn write_attr(&self, attr: &str, value: &str) -> Result<()> {
+        let path = self.path.join(attr);
+        std::fs::write(&path, value)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Channel numbers with a `<kind>N_input` attribute, e.g. fan1, fan2
+    pub fn channels(&self, kind: &str) -> Vec<u32> {
+        let Ok(entries) = std::fs::read_dir(&self.path) else {
+            return Vec::new();
+        };
+
+        let mut channels: Vec<u32> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file = entry.file_name();
+                file.to_str()?
+                    .strip_prefix(kind)?
+                    .strip_suffix("_input")?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        channels.sort_unstable();
+        channels
+    }
+
+    pub fn label(&self, kind: &str, channel: u32) -> Option<String> {
+        self.read_attr(&format!("{}{}_label", kind, channel))
+    }
+
+    pub fn input(&self, kind: &str, channel: u32) -> Option<i64> {
+        self.read_value(&format!("{}{}_input", kind, channel))
+    }
+
+    /// First channel whose label contains `needle` (case-insensitive)
+    pub fn channel_labeled(&self, kind: &str, needle: &str) -> Option<u32> {
+        self.channels(kind).into_iter().find(|c| {
+            self.label(kind, *c)
+                .is_some_and(|l| l.to_lowercase().contains(needle))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn devices_channels_and_labels() {
+        let root = std::env::temp_dir().join(format!("dell-hwmon-attrs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let coretemp = root.join("class/hwmon/hwmon2");
+        fs::create_dir_all(&coretemp).unwrap();
+        fs::create_dir_all(root.join("class/hwmon/hwmon9")).unwrap(); // no name
+        fs::create_dir_all(root.join("class/hwmon/hwmon1")).unwrap();
+        fs::write(root.join("class/hwmon/hwmon1/name"), "acpitz\n").unwrap();
+        fs::write(coretemp.join("name"), "coretemp\n").unwrap();
+        for (attr, value) in [
+            ("temp10_input", "60000"),
+            ("temp10_label", "Core 8"),
+            ("temp1_input", "72000"),
+            ("temp1_label", "Package id 0"),
+            ("temp2_input", "not a number"),
+            ("temp3_max", "100000"),
+        ] {
+            fs::write(coretemp.join(attr), format!("{}\n", value)).unwrap();
+        }
+
+        let names: Vec<String> = HwmonDevice::all(&root)
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["acpitz", "coretemp"]);
+
+        let device = HwmonDevice::find(&root, "coretemp").unwrap();
+        assert_eq!(device.channels("temp"), [1, 2, 10]);
+        assert!(device.channels("fan").is_empty());
+        assert_eq!(device.input("temp", 1), Some(72000));
+        assert_eq!(device.input("temp", 2), None);
+        assert_eq!(device.channel_labeled("temp", "core"), Some(10));
+        assert_eq!(device.channel_labeled("temp", "tctl"), None);
+        assert!(HwmonDevice::find(&root, "k10temp").is_none());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
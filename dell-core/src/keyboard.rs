@@ -3,27 +3,42 @@
 
 use crate::elc::{Action, Elc};
 use crate::elc_constants::*;
+use crate::gradient::Gradient;
+use crate::scene::{Effect, Lighting, Scene, ZoneEffect};
 use anyhow::{anyhow, Result};
 use rusb::{DeviceHandle, GlobalContext};
 use std::sync::{Arc, Mutex};
 
 const SUPPORTED_VENDOR_ID: u16 = 0x187c;
-const SUPPORTED_PRODUCT_IDS: [u16; 2] = [0x0550, 0x0551];
+/// ELC product IDs of the G-series keyboards
+pub const SUPPORTED_PRODUCT_IDS: [u16; 2] = [0x0550, 0x0551];
+const GRADIENT_SCROLL_FRAMES: usize = 6;
 
 pub struct KeyboardController {
     elc: Elc,
     handle: Arc<Mutex<DeviceHandle<GlobalContext>>>,
+    // Last lighting applied, kept so it can be exported as a scene
+    scene: Mutex<Option<Scene>>,
+    // Every lighting zone of this device; the first four are the keyboard
+    // zones, Alienware models add logo/lightbar zones after them
+    zones: Vec<u8>,
 }
 
 impl KeyboardController {
+    /// The first G-series keyboard found, with the usual four zones
     pub fn new(debug: bool) -> Result<Self> {
+        Self::open(&SUPPORTED_PRODUCT_IDS, ZONES_ALL.len() as u8, debug)
+    }
+
+    /// The first ELC device with one of `product_ids`, driving `zone_count` zones
+    pub fn open(product_ids: &[u16], zone_count: u8, debug: bool) -> Result<Self> {
         let mut device_handle: Option<DeviceHandle<GlobalContext>> = None;
 
         for device in rusb::devices()?.iter() {
             let device_desc = device.device_descriptor()?;
 
             if device_desc.vendor_id() == SUPPORTED_VENDOR_ID
-                && SUPPORTED_PRODUCT_IDS.contains(&device_desc.product_id())
+                && product_ids.contains(&device_desc.product_id())
             {
                 match device.open() {
                     Ok(h) => {
@@ -37,8 +52,13 @@ impl KeyboardController {
             }
         }
 
-        let device_handle = device_handle
-            .ok_or_else(|| anyhow!("No supported device found (187c:0550 or 187c:0551)"))?;
+        let device_handle = device_handle.ok_or_else(|| {
+            let ids: Vec<String> = product_ids
+                .iter()
+                .map(|id| format!("{:04x}:{:04x}", SUPPORTED_VENDOR_ID, id))
+                .collect();
+            anyhow!("No supported device found ({})", ids.join(" or "))
+        })?;
 
         // Reset device
         let _ = device_handle.reset();
@@ -57,13 +77,60 @@ impl KeyboardController {
         let handle = Arc::new(Mutex::new(device_handle));
         let elc = Elc::new(handle.clone(), debug);
 
-        Ok(Self { elc, handle })
+        Ok(Self {
+            elc,
+            handle,
+            scene: Mutex::new(None),
+            zones: (0..zone_count.clamp(1, MAX_ZONES)).collect(),
+        })
+    }
+
+    pub fn zone_count(&self) -> usize {
+        self.zones.len()
+    }
+
+    // Zones outside the numpad, for the static + morph mode
+    fn keyboard_zones(&self) -> Vec<u8> {
+        self.zones
+            .iter()
+            .copied()
+            .filter(|z| !ZONES_NP.contains(z))
+            .collect()
+    }
+
+    // Lighting with one entry per zone; zones past the fourth repeat the
+    // keyboard pattern
+    fn per_zone_lighting(&self, effect: impl Fn(usize) -> Effect) -> Lighting {
+        Lighting {
+            zones: self
+                .zones
+                .iter()
+                .map(|z| ZoneEffect {
+                    zones: vec![*z],
+                    effect: effect(*z as usize % ZONES_ALL.len()),
+                })
+                .collect(),
+        }
+    }
+
+    // Scene zones must exist on this device, not just fit the ELC range
+    fn check_zones(&self, lighting: &Lighting) -> Result<()> {
+        for entry in &lighting.zones {
+            if let Some(zone) = entry.zones.iter().find(|z| !self.zones.contains(z)) {
+                return Err(anyhow!(
+                    "Zone {} is not available on this keyboard (zones {:?})",
+                    zone,
+                    self.zones
+                ));
+            }
+        }
+        Ok(())
     }
 
     fn battery_flashing(&self) -> Result<()> {
         self.elc.remove_animation(DC_LOW)?;
         self.elc.start_new_animation(DC_LOW)?;
-        self.elc.start_series(&ZONES_ALL, 1)?;
+        self.elc.start_series(&self.zones, 1)?;
 
         // Red flashing
         self.elc.add_action(&[Action::new(
@@ -95,7 +162,7 @@ impl KeyboardController {
             TEMPO_MIN,
             AC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // AC Charged - Full brightness
@@ -107,7 +174,7 @@ impl KeyboardController {
             TEMPO_MIN,
             AC_CHARGED,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // AC Charging - Full brightness
@@ -119,7 +186,7 @@ impl KeyboardController {
             TEMPO_MIN,
             AC_CHARGING,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // DC Sleep - Off
@@ -131,7 +198,7 @@ impl KeyboardController {
             TEMPO_MIN,
             DC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // DC On - Half brightness
@@ -143,11 +210,14 @@ impl KeyboardController {
             TEMPO_MIN,
             DC_ON,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         self.battery_flashing()?;
 
+        self.remember(Some(Scene::new(Lighting::all(Effect::Static {
+            color: [red, green, blue],
+        }))));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
@@ -164,12 +234,18 @@ impl KeyboardController {
             TEMPO_MIN,
             AC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // AC Charged - Full brightness morph
         self.apply_morph_action(
-            red, green, blue, duration, TEMPO_MIN, AC_CHARGED, &ZONES_ALL,
+            red,
+            green,
+            blue,
+            duration,
+            TEMPO_MIN,
+            AC_CHARGED,
+            &self.zones,
         )?;
 
         // AC Charging - Full brightness morph
@@ -180,7 +256,7 @@ impl KeyboardController {
             duration,
             TEMPO_MIN,
             AC_CHARGING,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // DC Sleep - Off
@@ -192,7 +268,7 @@ impl KeyboardController {
             TEMPO_MIN,
             DC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // DC On - Half brightness morph
@@ -203,11 +279,19 @@ impl KeyboardController {
             duration,
             TEMPO_MIN,
             DC_ON,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         self.battery_flashing()?;
 
+        self.remember(Some(Scene::new(Lighting::all(Effect::Morph {
+            colors: vec![
+                [red, green, blue],
+                [255 - red, 255 - green, 255 - blue],
+                [red, green, blue],
+            ],
+            duration,
+        }))));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
@@ -270,6 +354,27 @@ impl KeyboardController {
         )?;
 
         self.battery_flashing()?;
+        self.remember(Some(Scene::new(Lighting {
+            zones: vec![
+                ZoneEffect {
+                    zones: self.keyboard_zones(),
+                    effect: Effect::Static {
+                        color: [red, green, blue],
+                    },
+                },
+                ZoneEffect {
+                    zones: ZONES_NP.to_vec(),
+                    effect: Effect::Morph {
+                        colors: vec![
+                            [red_morph, green_morph, blue_morph],
+                            [green_morph, blue_morph, red_morph],
+                            [blue_morph, red_morph, green_morph],
+                        ],
+                        duration,
+                    },
+                },
+            ],
+        })));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
@@ -287,7 +392,7 @@ impl KeyboardController {
             TEMPO_MIN,
             AC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // AC Charged - Pulse
@@ -299,7 +404,7 @@ impl KeyboardController {
             tempo,
             AC_CHARGED,
             PULSE,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // AC Charging - Pulse
@@ -311,7 +416,7 @@ impl KeyboardController {
             tempo,
             AC_CHARGING,
             PULSE,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // DC Sleep - Off
@@ -323,7 +428,7 @@ impl KeyboardController {
             TEMPO_MIN,
             DC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         // DC On - Pulse
@@ -335,10 +440,18 @@ impl KeyboardController {
             tempo,
             DC_ON,
             PULSE,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         self.battery_flashing()?;
+        let lighting = Lighting::all(Effect::Pulse {
+            color: [red, green, blue],
+            tempo,
+        });
+        self.remember(Some(Scene {
+            dc_on: Some(lighting.clone()),
+            ..Scene::new(lighting)
+        }));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
@@ -398,6 +511,8 @@ impl KeyboardController {
         )?;
 
         self.battery_flashing()?;
+        // Other zones keep whatever they had, which we don't track
+        self.remember(None);
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
@@ -410,8 +525,8 @@ impl KeyboardController {
             self.elc.remove_animation(animation)?;
             self.elc.start_new_animation(animation)?;
 
-            for (zone_idx, color) in colors.iter().enumerate() {
-                let zone = zone_idx as u8;
+            for &zone in &self.zones {
+                let color = colors[zone as usize % colors.len()];
                 let r = color[0] / dim_factor;
                 let g = color[1] / dim_factor;
                 let b = color[2] / dim_factor;
@@ -437,7 +552,7 @@ impl KeyboardController {
             TEMPO_MIN,
             AC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
         self.apply_action(
             0,
@@ -447,7 +562,7 @@ impl KeyboardController {
             TEMPO_MIN,
             DC_SLEEP,
             COLOR,
-            &ZONES_ALL,
+            &self.zones,
         )?;
 
         apply_multizone(AC_CHARGED, 1)?;
@@ -455,9 +570,93 @@ impl KeyboardController {
         apply_multizone(DC_ON, 2)?; // Half brightness
 
         self.battery_flashing()?;
+        self.remember(Some(Scene::new(
+            self.per_zone_lighting(|i| Effect::Static { color: colors[i] }),
+        )));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
+
+    pub fn set_gradient(&self, gradient: &Gradient) -> Result<()> {
+        self.set_four_zone_colors(&gradient.zone_colors())
+    }
+
+    pub fn set_scrolling_gradient(&self, gradient: &Gradient, speed: u16) -> Result<()> {
+        self.set_dim(0)?;
+
+        // One frame per step of the scroll; each zone morphs through the
+        // same frames offset by its position so the gradient moves sideways
+        let frames: Vec<[[u8; 3]; 4]> = (0..GRADIENT_SCROLL_FRAMES)
+            .map(|f| gradient.zone_colors_shifted(f as f32 * 2.0 / GRADIENT_SCROLL_FRAMES as f32))
+            .collect();
+
+        let build_scroll = |animation: u16, dim_factor: u8| -> Result<()> {
+            self.elc.remove_animation(animation)?;
+            self.elc.start_new_animation(animation)?;
+
+            for &zone in &self.zones {
+                let actions: Vec<Action> = frames
+                    .iter()
+                    .map(|frame| {
+                        let c = frame[zone as usize % frame.len()];
+                        Action::new(
+                            MORPH,
+                            speed,
+                            TEMPO_MIN,
+                            c[0] / dim_factor,
+                            c[1] / dim_factor,
+                            c[2] / dim_factor,
+                        )
+                    })
+                    .collect();
+
+                self.elc.start_series(&[zone], 0)?;
+                for chunk in actions.chunks(3) {
+                    self.elc.add_action(chunk)?;
+                }
+            }
+
+            self.elc.finish_save_animation(animation)?;
+            self.elc.set_default_animation(animation)?;
+            Ok(())
+        };
+
+        self.apply_action(
+            0,
+            0,
+            0,
+            DURATION_MAX,
+            TEMPO_MIN,
+            AC_SLEEP,
+            COLOR,
+            &self.zones,
+        )?;
+        self.apply_action(
+            0,
+            0,
+            0,
+            DURATION_MAX,
+            TEMPO_MIN,
+            DC_SLEEP,
+            COLOR,
+            &self.zones,
+        )?;
+
+        build_scroll(AC_CHARGED, 1)?;
+        build_scroll(AC_CHARGING, 1)?;
+        build_scroll(DC_ON, 2)?; // Half brightness
+
+        self.battery_flashing()?;
+        self.remember(Some(Scene::new(self.per_zone_lighting(|i| {
+            Effect::Morph {
+                colors: frames.iter().map(|f| f[i]).collect(),
+                duration: speed,
+            }
+        }))));
+        let _ = self.handle.lock().unwrap().reset();
+        Ok(())
+    }
+
     pub fn remove_all_animations(&self) -> Result<()> {
         // Turn off LEDs by setting to black
         self.set_static(0, 0, 0)?;
@@ -482,7 +681,7 @@ impl KeyboardController {
         let build_cycle = |animation: u16| -> Result<()> {
             self.elc.remove_animation(animation)?;
             self.elc.start_new_animation(animation)?;
-            self.elc.start_series(&ZONES_ALL, 0)?; // 0 loop count = infinite?
+            self.elc.start_series(&self.zones, 0)?; // 0 loop count = infinite?
 
             // Cycle: Red -> Yellow -> Green -> Cyan -> Blue -> Magenta -> Red
             // Simplification: Red -> Green -> Blue -> Red
@@ -503,6 +702,14 @@ impl KeyboardController {
         build_cycle(DC_ON)?; // Should be dimmer ideally but for now full brightness spectrum
 
         self.battery_flashing()?;
+        let lighting = Lighting::all(Effect::Morph {
+            colors: vec![[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 0, 0]],
+            duration: speed,
+        });
+        self.remember(Some(Scene {
+            dc_on: Some(lighting.clone()),
+            ..Scene::new(lighting)
+        }));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
@@ -562,15 +769,83 @@ impl KeyboardController {
         build_wave(DC_ON)?;
 
         self.battery_flashing()?;
+        let cycle = |start: usize| -> Effect {
+            let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+            Effect::Morph {
+                colors: (0..4).map(|i| colors[(start + i) % 3]).collect(),
+                duration: speed,
+            }
+        };
+        let lighting = Lighting::per_zone([cycle(0), cycle(1), cycle(2), cycle(0)]);
+        self.remember(Some(Scene {
+            dc_on: Some(lighting.clone()),
+            ..Scene::new(lighting)
+        }));
         let _ = self.handle.lock().unwrap().reset();
         Ok(())
     }
 
     pub fn set_dim(&self, level: u8) -> Result<()> {
-        self.elc.dim(&ZONES_ALL, level)?;
+        self.elc.dim(&self.zones, level)?;
+        if let Some(scene) = self.scene.lock().unwrap().as_mut() {
+            scene.brightness = 100 - level.min(100);
+        }
         Ok(())
     }
 
+    pub fn apply_scene(&self, scene: &Scene) -> Result<()> {
+        scene.validate()?;
+        let resolved = scene.resolved();
+        for (_, lighting) in &resolved {
+            self.check_zones(lighting)?;
+        }
+        self.set_dim(0)?;
+
+        for (animation, lighting) in &resolved {
+            self.elc.remove_animation(*animation)?;
+            self.elc.start_new_animation(*animation)?;
+
+            for entry in &lighting.zones {
+                // Morph cycles forever, the other effects play once
+                let loops = if matches!(entry.effect, Effect::Morph { .. }) {
+                    0
+                } else {
+                    1
+                };
+                self.elc.start_series(&entry.zones, loops)?;
+                for chunk in entry.effect.actions().chunks(3) {
+                    self.elc.add_action(chunk)?;
+                }
+            }
+
+            self.elc.finish_save_animation(*animation)?;
+            self.elc.set_default_animation(*animation)?;
+        }
+
+        if scene.dc_low.is_none() {
+            self.battery_flashing()?;
+        }
+
+        self.set_dim(100 - scene.brightness.min(100))?;
+        self.remember(Some(scene.clone()));
+
+        let _ = self.handle.lock().unwrap().reset();
+        Ok(())
+    }
+
+    /// The current lighting as a scene
+    pub fn export_scene(&self) -> Result<Scene> {
+        self.scene
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow!("Current lighting is unknown; apply an effect first"))
+    }
+
+    fn remember(&self, scene: Option<Scene>) {
+        *self.scene.lock().unwrap() = scene;
+    }
+
     fn apply_action(
         &self,
         red: u8,
@@ -642,7 +917,7 @@ impl KeyboardController {
         ])?;
 
         // Keyboard - Static
-        self.elc.start_series(&ZONES_KB, 1)?;
+        self.elc.start_series(&self.keyboard_zones(), 1)?;
         self.elc
             .add_action(&[Action::new(COLOR, duration, tempo, red, green, blue)])?;
 
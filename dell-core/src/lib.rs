@@ -2,14 +2,55 @@
 // Provides USB LED control and ACPI power management
 
 pub mod acpi;
+pub mod acpi_backend;
+pub mod acpi_value;
+pub mod config;
 pub mod elc;
 pub mod elc_constants;
+pub mod fan_calibration;
+pub mod fan_curve;
+pub mod gradient;
+pub mod hardware;
 pub mod hid_report;
+pub mod hwmon;
 pub mod keyboard;
+pub mod models;
+pub mod palette;
+pub mod power_mode;
+pub mod rpm_control;
+pub mod scene;
+pub mod sensors;
+pub mod sysfs_backend;
+pub mod theme;
+pub mod thermal_restore;
+pub mod thermal_watchdog;
+pub mod wallpaper;
+pub mod wmax;
 
 // Re-export commonly used types
-pub use acpi::{AcpiController, LaptopModel};
+pub use acpi::{AcpiController, FanState, SensorReadings};
+pub use acpi_backend::{
+    AcpiBackend, FakeAcpiBackend, HelperBackend, PkexecBackend, ProcAcpiBackend,
+};
+pub use acpi_value::{AcpiCallError, AcpiValue};
+pub use fan_calibration::{
+    Anomaly, CalibrationProgress, CalibrationReport, CalibrationSettings, FanCalibration,
+};
+pub use fan_curve::{CurvePoint, FanCurve, FanCurveConfig, FanCurveController, TempSource};
+pub use gradient::{ColorSpace, ColorStop, Gradient};
+pub use hardware::HardwareInfo;
 pub use keyboard::KeyboardController;
+pub use models::{CpuVendor, LaptopModel, ModelDatabase, Quirk};
+pub use palette::{Palette, PaletteLibrary};
+pub use power_mode::PowerMode;
+pub use rpm_control::{PiController, RpmController, RpmTargetConfig};
+pub use scene::Scene;
+pub use sensors::{HwmonSensors, SensorKind, SensorReading};
+pub use sysfs_backend::SysfsBackend;
+pub use theme::{Theme, ThemeWatcher, ZoneMapping};
+pub use thermal_restore::RestoreConfig;
+pub use thermal_watchdog::{Intervention, ThermalWatchdog, WatchdogConfig};
+pub use wmax::{FanId, SensorId, WmaxCommand, WmaxSensor};
 
 /// Initialize logging for the library
 pub fn init_logging() {
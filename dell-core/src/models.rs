@@ -0,0 +1,434 @@
+// Laptop model database
+// Built-in models come from data/models.toml; a models.toml in the config
+// directory can add models or replace built-in ones by id.
+
+use crate::acpi::INTEL_ACPI_PATH;
+use crate::config;
+use crate::elc_constants::MAX_ZONES;
+use crate::hardware::HardwareInfo;
+use crate::keyboard::SUPPORTED_PRODUCT_IDS;
+use crate::power_mode::PowerMode;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const EMBEDDED_MODELS: &str = include_str!("../data/models.toml");
+const MODELS_FILE: &str = "models.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Quirk {
+    /// The EC ignores manual fan boost
+    FanControlLimited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuVendor {
+    Intel,
+    Amd,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LaptopModel {
+    pub id: String,
+    pub name: String,
+    /// DMI product_sku values (checked before the keywords)
+    #[serde(default)]
+    pub skus: Vec<String>,
+    #[serde(default)]
+    pub dmi_keywords: Vec<String>,
+    /// Only match machines with this CPU vendor (same DMI name, other platform)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<CpuVendor>,
+    pub acpi_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_code: Option<u64>,
+    pub power_modes: Vec<PowerMode>,
+    #[serde(default = "default_fans")]
+    pub fans: u8,
+    #[serde(default)]
+    pub keyboard_zones: u8,
+    #[serde(default = "default_keyboard_product_ids")]
+    pub keyboard_product_ids: Vec<u16>,
+    #[serde(default)]
+    pub quirks: Vec<Quirk>,
+}
+
+fn default_fans() -> u8 {
+    2
+}
+
+fn default_keyboard_product_ids() -> Vec<u16> {
+    SUPPORTED_PRODUCT_IDS.to_vec()
+}
+
+impl LaptopModel {
+    /// Generic configuration for machines not in the database
+    pub fn unknown() -> Self {
+        Self {
+            id: "unknown".to_string(),
+            name: "Unknown".to_string(),
+            skus: Vec::new(),
+            dmi_keywords: Vec::new(),
+            cpu: None,
+            acpi_path: INTEL_ACPI_PATH.to_string(),
+            model_code: None,
+            power_modes: PowerMode::ALL
+                .into_iter()
+                .filter(|m| *m != PowerMode::Cool)
+                .collect(),
+            fans: default_fans(),
+            keyboard_zones: 0,
+            keyboard_product_ids: default_keyboard_product_ids(),
+            quirks: Vec::new(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.id == "unknown"
+    }
+
+    pub fn supports_keyboard(&self) -> bool {
+        self.keyboard_zones > 0
+    }
+
+    pub fn has_quirk(&self, quirk: Quirk) -> bool {
+        self.quirks.contains(&quirk)
+    }
+
+    /// Every DMI keyword appears in `product_name` (case-insensitive)
+    pub fn matches_product(&self, product_name: &str) -> bool {
+        let product = product_name.to_lowercase();
+        !self.dmi_keywords.is_empty()
+            && self
+                .dmi_keywords
+                .iter()
+                .all(|k| product.contains(&k.to_lowercase()))
+    }
+
+    pub fn matches_cpu(&self, hardware: &HardwareInfo) -> bool {
+        match self.cpu {
+            Some(CpuVendor::Amd) => hardware.is_amd(),
+            Some(CpuVendor::Intel) => !hardware.is_amd(),
+            None => true,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.id.is_empty() || self.name.is_empty() {
+            return Err(anyhow!("Model entries need an id and a name"));
+        }
+        if self.power_modes.is_empty() {
+            return Err(anyhow!("Model '{}' has no power modes", self.id));
+        }
+        if self.keyboard_zones > MAX_ZONES {
+            return Err(anyhow!(
+                "Model '{}': keyboard_zones must be 0-{}",
+                self.id,
+                MAX_ZONES
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ModelFile {
+    #[serde(default)]
+    model: Vec<LaptopModel>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelDatabase {
+    models: Vec<LaptopModel>,
+}
+
+impl ModelDatabase {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: ModelFile = toml::from_str(content)?;
+        for model in &file.model {
+            model.validate()?;
+        }
+        Ok(Self { models: file.model })
+    }
+
+    /// The database shipped with the library
+    pub fn embedded() -> Self {
+        Self::from_toml(EMBEDDED_MODELS).expect("embedded models.toml is invalid")
+    }
+
+    /// Built-in models plus the user's override file, if any
+    pub fn load_default() -> Self {
+        let mut db = Self::embedded();
+        match config::config_file(MODELS_FILE) {
+            Ok(path) => {
+                if let Err(e) = db.merge_file(&path) {
+                    warn!("Ignoring {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => warn!("No config directory for {}: {}", MODELS_FILE, e),
+        }
+        db
+    }
+
+    /// Merge models from `path`; missing files are ignored
+    pub fn merge_file(&mut self, path: &Path) -> Result<()> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+        };
+        let overrides = Self::from_toml(&content)
+            .with_context(|| format!("Invalid model file {}", path.display()))?;
+        info!(
+            "Loaded {} model(s) from {}",
+            overrides.models.len(),
+            path.display()
+        );
+        self.merge(overrides);
+        Ok(())
+    }
+
+    /// Models in `other` replace ours with the same id; new ones are
+    /// matched before the existing entries
+    pub fn merge(&mut self, other: Self) {
+        let mut added = Vec::new();
+        for model in other.models {
+            match self.models.iter_mut().find(|m| m.id == model.id) {
+                Some(existing) => *existing = model,
+                None => added.push(model),
+            }
+        }
+        added.append(&mut self.models);
+        self.models = added;
+    }
+
+    pub fn match_product(&self, product_name: &str) -> Option<&LaptopModel> {
+        self.models.iter().find(|m| m.matches_product(product_name))
+    }
+
+    /// Match by SKU first, then by product name keywords, skipping models
+    /// for the other CPU vendor
+    pub fn match_hardware(&self, hardware: &HardwareInfo) -> Option<&LaptopModel> {
+        let sku = hardware.product_sku.trim();
+        let candidates = || self.models.iter().filter(|m| m.matches_cpu(hardware));
+        candidates()
+            .find(|m| !sku.is_empty() && m.skus.iter().any(|s| s.eq_ignore_ascii_case(sku)))
+            .or_else(|| candidates().find(|m| m.matches_product(&hardware.product_name)))
+    }
+
+    pub fn match_code(&self, acpi_path: &str, code: u64) -> Option<&LaptopModel> {
+        self.models
+            .iter()
+            .find(|m| m.acpi_path == acpi_path && m.model_code == Some(code))
+    }
+
+    /// Distinct ACPI paths, in database order, for probing
+    pub fn acpi_paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = Vec::new();
+        for model in &self.models {
+            if !paths.contains(&model.acpi_path.as_str()) {
+                paths.push(&model.acpi_path);
+            }
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acpi::AMD_ACPI_PATH;
+
+    const CUSTOM_MODEL: &str = r#"
+        [[model]]
+        id = "custom-g15"
+        name = "My G15"
+        dmi_keywords = ["g15"]
+        acpi_path = '\_SB.AMWW.WMAX'
+        power_modes = ["USTT_Balanced", "Manual"]
+    "#;
+
+    fn hardware(product: &str, cpu_vendor: &str) -> HardwareInfo {
+        HardwareInfo {
+            product_name: product.to_string(),
+            cpu_vendor: cpu_vendor.to_string(),
+            ..HardwareInfo::default()
+        }
+    }
+
+    fn matched_id(db: &ModelDatabase, product: &str, cpu_vendor: &str) -> Option<String> {
+        db.match_hardware(&hardware(product, cpu_vendor))
+            .map(|m| m.id.clone())
+    }
+
+    #[test]
+    fn embedded_database_loads() {
+        let db = ModelDatabase::embedded();
+        assert!(!db.models.is_empty());
+        let mut ids: Vec<&str> = db.models.iter().map(|m| m.id.as_str()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), db.models.len(), "duplicate model ids");
+        assert_eq!(db.acpi_paths(), [INTEL_ACPI_PATH, AMD_ACPI_PATH]);
+    }
+
+    #[test]
+    fn matches_by_product_name_and_cpu() {
+        let db = ModelDatabase::embedded();
+        assert_eq!(
+            matched_id(&db, "Dell G15 5530", "GenuineIntel").as_deref(),
+            Some("g15-5530")
+        );
+        assert_eq!(
+            matched_id(&db, "Dell G15 5511", "AuthenticAMD").as_deref(),
+            Some("g15-5511-amd")
+        );
+        assert_eq!(
+            matched_id(&db, "Dell G15 5511", "GenuineIntel").as_deref(),
+            Some("g15-5511")
+        );
+        assert_eq!(matched_id(&db, "Dell G15", "GenuineIntel"), None);
+        assert_eq!(matched_id(&db, "Inspiron 5530", "GenuineIntel"), None);
+    }
+
+    #[test]
+    fn sku_is_checked_before_the_product_name() {
+        let db = ModelDatabase::from_toml(
+            r#"
+            [[model]]
+            id = "by-name"
+            name = "By name"
+            dmi_keywords = ["g15", "5530"]
+            acpi_path = '\_SB.AMWW.WMAX'
+            power_modes = ["Manual"]
+
+            [[model]]
+            id = "by-sku"
+            name = "By SKU"
+            skus = ["0C05"]
+            acpi_path = '\_SB.AMWW.WMAX'
+            power_modes = ["Manual"]
+            "#,
+        )
+        .unwrap();
+        let hardware = |sku: &str| HardwareInfo {
+            product_sku: sku.to_string(),
+            ..hardware("Dell G15 5530", "GenuineIntel")
+        };
+        let id = |sku| db.match_hardware(&hardware(sku)).map(|m| m.id.as_str());
+        assert_eq!(id("0C05"), Some("by-sku"));
+        assert_eq!(id("0c05\n"), Some("by-sku"));
+        assert_eq!(id("0BD8"), Some("by-name"));
+        assert_eq!(id(""), Some("by-name"));
+    }
+
+    #[test]
+    fn matches_dmi_read_from_sysfs() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/dmi-g15-5530");
+        let hardware = HardwareInfo::read_from(&root);
+        let db = ModelDatabase::embedded();
+        assert_eq!(
+            db.match_hardware(&hardware).map(|m| m.id.as_str()),
+            Some("g15-5530")
+        );
+    }
+
+    #[test]
+    fn matches_by_model_code_on_its_path() {
+        let db = ModelDatabase::embedded();
+        let id = |path, code| db.match_code(path, code).map(|m| m.id.as_str());
+        assert_eq!(id(INTEL_ACPI_PATH, 0x12c0), Some("g15-5520"));
+        assert_eq!(id(AMD_ACPI_PATH, 0x12c0), Some("g15-5525"));
+        assert_eq!(id(AMD_ACPI_PATH, 0x1234), None);
+    }
+
+    #[test]
+    fn defaults_for_optional_fields() {
+        let db = ModelDatabase::from_toml(CUSTOM_MODEL).unwrap();
+        let model = &db.models[0];
+        assert_eq!(model.fans, 2);
+        assert_eq!(model.keyboard_zones, 0);
+        assert!(!model.supports_keyboard());
+        assert_eq!(model.keyboard_product_ids, SUPPORTED_PRODUCT_IDS);
+        assert!(model.quirks.is_empty());
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        let invalid = [
+            "[[model]]\nid = ",
+            // Missing acpi_path
+            "[[model]]\nid = \"x\"\nname = \"X\"\npower_modes = [\"Manual\"]",
+            // Unknown power mode
+            "[[model]]\nid = \"x\"\nname = \"X\"\nacpi_path = 'p'\npower_modes = [\"Turbo\"]",
+            "[[model]]\nid = \"x\"\nname = \"X\"\nacpi_path = 'p'\npower_modes = []",
+            "[[model]]\nid = \"\"\nname = \"X\"\nacpi_path = 'p'\npower_modes = [\"Manual\"]",
+            "[[model]]\nid = \"x\"\nname = \"X\"\nacpi_path = 'p'\npower_modes = [\"Manual\"]\n\
+             keyboard_zones = 200",
+            "[[model]]\nid = \"x\"\nname = \"X\"\nacpi_path = 'p'\npower_modes = [\"Manual\"]\n\
+             quirks = [\"no-such-quirk\"]",
+        ];
+        for content in invalid {
+            assert!(ModelDatabase::from_toml(content).is_err(), "{}", content);
+        }
+    }
+
+    #[test]
+    fn merge_replaces_by_id_and_checks_new_models_first() {
+        let mut db = ModelDatabase::embedded();
+        let count = db.models.len();
+        db.merge(
+            ModelDatabase::from_toml(
+                r#"
+                [[model]]
+                id = "g15-5520"
+                name = "G15 5520 (custom)"
+                dmi_keywords = ["g15", "5520"]
+                acpi_path = '\_SB.AMWW.WMAX'
+                power_modes = ["USTT_Balanced", "Manual"]
+                quirks = ["fan-control-limited"]
+                "#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(db.models.len(), count);
+        let model = db.match_product("Dell G15 5520").unwrap();
+        assert_eq!(model.name, "G15 5520 (custom)");
+        assert!(model.has_quirk(Quirk::FanControlLimited));
+
+        db.merge(ModelDatabase::from_toml(CUSTOM_MODEL).unwrap());
+        assert_eq!(db.models.len(), count + 1);
+        assert_eq!(db.match_product("Dell G15 5530").unwrap().id, "custom-g15");
+        assert_eq!(db.match_product("Dell G16 7620").unwrap().id, "g16-7620");
+    }
+
+    #[test]
+    fn merge_file_ignores_missing_files_only() {
+        let dir = std::env::temp_dir().join(format!("dell-models-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut db = ModelDatabase::embedded();
+        let count = db.models.len();
+
+        assert!(db.merge_file(&dir.join("missing.toml")).is_ok());
+        assert_eq!(db.models.len(), count);
+
+        let bad = dir.join("bad.toml");
+        std::fs::write(&bad, "[[model]]\nid = \"x\"").unwrap();
+        assert!(db.merge_file(&bad).is_err());
+        assert_eq!(db.models.len(), count);
+
+        let good = dir.join("models.toml");
+        std::fs::write(&good, CUSTOM_MODEL).unwrap();
+        db.merge_file(&good).unwrap();
+        assert_eq!(db.models.len(), count + 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,276 @@
+// Named lighting palettes: built-ins plus user palettes persisted as JSON
+
+use crate::config;
+use crate::gradient::{ColorSpace, Gradient};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const PALETTES_FILE: &str = "palettes.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<[u8; 3]>,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+impl Palette {
+    pub fn new(name: &str, colors: Vec<[u8; 3]>) -> Self {
+        Self {
+            name: name.to_string(),
+            colors,
+            builtin: false,
+        }
+    }
+
+    fn builtin(name: &str, colors: &[[u8; 3]]) -> Self {
+        Self {
+            name: name.to_string(),
+            colors: colors.to_vec(),
+            builtin: true,
+        }
+    }
+
+    /// One color fills every zone, four map 1:1, anything else is spread
+    /// across the zones as an even gradient
+    pub fn zone_colors(&self) -> Result<[[u8; 3]; 4]> {
+        match self.colors.len() {
+            0 => Err(anyhow!("Palette '{}' has no colors", self.name)),
+            1 => Ok([self.colors[0]; 4]),
+            4 => Ok([
+                self.colors[0],
+                self.colors[1],
+                self.colors[2],
+                self.colors[3],
+            ]),
+            _ => Ok(Gradient::even(&self.colors, ColorSpace::Oklab)?.zone_colors()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PaletteFile {
+    palettes: Vec<Palette>,
+}
+
+pub fn builtin_palettes() -> Vec<Palette> {
+    vec![
+        Palette::builtin("Vermelho", &[[255, 0, 0]]),
+        Palette::builtin("Verde", &[[0, 255, 0]]),
+        Palette::builtin("Azul", &[[0, 0, 255]]),
+        Palette::builtin("Amarelo", &[[255, 255, 0]]),
+        Palette::builtin("Ciano", &[[0, 255, 255]]),
+        Palette::builtin("Magenta", &[[255, 0, 255]]),
+        Palette::builtin("Branco", &[[255, 255, 255]]),
+        Palette::builtin("Laranja", &[[255, 128, 0]]),
+        Palette::builtin("Roxo", &[[128, 0, 255]]),
+        Palette::builtin("Rosa", &[[255, 128, 192]]),
+        Palette::builtin(
+            "Arco-íris",
+            &[[255, 0, 0], [255, 255, 0], [0, 255, 0], [0, 0, 255]],
+        ),
+        Palette::builtin("Pôr do Sol", &[[255, 64, 0], [255, 0, 128], [128, 0, 255]]),
+        Palette::builtin("Oceano", &[[0, 64, 255], [0, 255, 255]]),
+    ]
+}
+
+pub struct PaletteLibrary {
+    path: PathBuf,
+    user: Vec<Palette>,
+}
+
+impl PaletteLibrary {
+    /// Library stored in the user's config directory
+    pub fn load_default() -> Result<Self> {
+        Self::load(&config::config_file(PALETTES_FILE)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file: PaletteFile = config::load_json(path)?.unwrap_or_default();
+        let user = file
+            .palettes
+            .into_iter()
+            .map(|mut p| {
+                p.builtin = false;
+                p
+            })
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            user,
+        })
+    }
+
+    pub fn list(&self) -> Vec<Palette> {
+        let mut all = builtin_palettes();
+        all.extend(self.user.iter().cloned());
+        all
+    }
+
+    pub fn get(&self, name: &str) -> Option<Palette> {
+        self.list().into_iter().find(|p| p.name == name)
+    }
+
+    /// Add or replace a user palette
+    pub fn save(&mut self, palette: Palette) -> Result<()> {
+        if palette.name.trim().is_empty() {
+            return Err(anyhow!("Palette name cannot be empty"));
+        }
+        if palette.colors.is_empty() {
+            return Err(anyhow!("Palette '{}' has no colors", palette.name));
+        }
+        if builtin_palettes().iter().any(|p| p.name == palette.name) {
+            return Err(anyhow!("'{}' is a built-in palette", palette.name));
+        }
+
+        let palette = Palette {
+            builtin: false,
+            ..palette
+        };
+        match self.user.iter_mut().find(|p| p.name == palette.name) {
+            Some(existing) => *existing = palette,
+            None => self.user.push(palette),
+        }
+        self.persist()
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<()> {
+        if builtin_palettes().iter().any(|p| p.name == name) {
+            return Err(anyhow!("Built-in palette '{}' cannot be deleted", name));
+        }
+        let before = self.user.len();
+        self.user.retain(|p| p.name != name);
+        if self.user.len() == before {
+            return Err(anyhow!("Palette '{}' not found", name));
+        }
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<()> {
+        config::save_json(
+            &self.path,
+            &PaletteFile {
+                palettes: self.user.clone(),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Library file in its own temp dir, removed on drop
+    struct TempLibrary {
+        dir: PathBuf,
+    }
+
+    impl TempLibrary {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("dell-palettes-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self { dir }
+        }
+
+        fn path(&self) -> PathBuf {
+            self.dir.join(PALETTES_FILE)
+        }
+    }
+
+    impl Drop for TempLibrary {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_library() {
+        let temp = TempLibrary::new("missing");
+        let library = PaletteLibrary::load(&temp.path()).unwrap();
+        assert_eq!(library.list(), builtin_palettes());
+        assert!(!temp.path().exists());
+    }
+
+    #[test]
+    fn saved_palettes_survive_a_reload() {
+        let temp = TempLibrary::new("round-trip");
+        let mut library = PaletteLibrary::load(&temp.path()).unwrap();
+        library
+            .save(Palette::new("Neon", vec![[255, 0, 128], [0, 255, 200]]))
+            .unwrap();
+        library
+            .save(Palette::new("Dim", vec![[10, 10, 10]]))
+            .unwrap();
+        // Saving under the same name replaces it
+        library
+            .save(Palette::new("Dim", vec![[20, 20, 20]]))
+            .unwrap();
+
+        let reloaded = PaletteLibrary::load(&temp.path()).unwrap();
+        let user: Vec<Palette> = reloaded.list().into_iter().filter(|p| !p.builtin).collect();
+        assert_eq!(
+            user,
+            [
+                Palette::new("Neon", vec![[255, 0, 128], [0, 255, 200]]),
+                Palette::new("Dim", vec![[20, 20, 20]]),
+            ]
+        );
+        assert!(reloaded.get("Vermelho").unwrap().builtin);
+    }
+
+    #[test]
+    fn invalid_palettes_are_not_saved() {
+        let temp = TempLibrary::new("invalid");
+        let mut library = PaletteLibrary::load(&temp.path()).unwrap();
+        assert!(library.save(Palette::new(" ", vec![[1, 2, 3]])).is_err());
+        assert!(library.save(Palette::new("Empty", Vec::new())).is_err());
+        assert!(library
+            .save(Palette::new("Vermelho", vec![[1, 2, 3]]))
+            .is_err());
+        assert!(!temp.path().exists());
+    }
+
+    #[test]
+    fn delete_removes_user_palettes_only() {
+        let temp = TempLibrary::new("delete");
+        let mut library = PaletteLibrary::load(&temp.path()).unwrap();
+        library
+            .save(Palette::new("Neon", vec![[255, 0, 128]]))
+            .unwrap();
+
+        assert!(library.delete("Vermelho").is_err());
+        assert!(library.delete("Unknown").is_err());
+        library.delete("Neon").unwrap();
+        assert!(library.get("Neon").is_none());
+
+        let reloaded = PaletteLibrary::load(&temp.path()).unwrap();
+        assert_eq!(reloaded.list(), builtin_palettes());
+    }
+
+    #[test]
+    fn zone_colors_by_palette_size() {
+        let red = [255, 0, 0];
+        assert_eq!(
+            Palette::new("One", vec![red]).zone_colors().unwrap(),
+            [red; 4]
+        );
+        let four = vec![[1, 1, 1], [2, 2, 2], [3, 3, 3], [4, 4, 4]];
+        assert_eq!(
+            Palette::new("Four", four.clone())
+                .zone_colors()
+                .unwrap()
+                .to_vec(),
+            four
+        );
+        let two = Palette::new("Two", vec![[0, 0, 0], [255, 255, 255]])
+            .zone_colors()
+            .unwrap();
+        assert_eq!(two[0], [0, 0, 0]);
+        assert_eq!(two[3], [255, 255, 255]);
+        assert!(Palette::new("None", Vec::new()).zone_colors().is_err());
+    }
+}
@@ -0,0 +1,89 @@
+// Thermal power modes (USTT tables)
+// Which modes a model accepts is listed in the model database (models.rs)
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerMode {
+    #[serde(rename = "USTT_Balanced")]
+    Balanced,
+    #[serde(rename = "USTT_Performance")]
+    Performance,
+    #[serde(rename = "USTT_Cool")]
+    Cool,
+    #[serde(rename = "USTT_Quiet")]
+    Quiet,
+    #[serde(rename = "USTT_FullSpeed")]
+    FullSpeed,
+    #[serde(rename = "USTT_BatterySaver")]
+    BatterySaver,
+    #[serde(rename = "G Mode")]
+    GMode,
+    Manual,
+}
+
+impl PowerMode {
+    pub const ALL: [PowerMode; 8] = [
+        Self::Balanced,
+        Self::Performance,
+        Self::Cool,
+        Self::Quiet,
+        Self::FullSpeed,
+        Self::BatterySaver,
+        Self::GMode,
+        Self::Manual,
+    ];
+
+    /// Thermal table code passed to `SetPowerMode`
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Balanced => 0xa0,
+            Self::Performance => 0xa1,
+            Self::Cool => 0xa2,
+            Self::Quiet => 0xa3,
+            Self::FullSpeed => 0xa4,
+            Self::BatterySaver => 0xa5,
+            Self::GMode => 0xab,
+            Self::Manual => 0x00,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.code() == code)
+    }
+
+    /// Identifier shared with the frontend and settings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Balanced => "USTT_Balanced",
+            Self::Performance => "USTT_Performance",
+            Self::Cool => "USTT_Cool",
+            Self::Quiet => "USTT_Quiet",
+            Self::FullSpeed => "USTT_FullSpeed",
+            Self::BatterySaver => "USTT_BatterySaver",
+            Self::GMode => "G Mode",
+            Self::Manual => "Manual",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Balanced => "Balanceado",
+            Self::Performance => "Performance",
+            Self::Cool => "Frio",
+            Self::Quiet => "Silencioso",
+            Self::FullSpeed => "Velocidade Máxima",
+            Self::BatterySaver => "Economia de Bateria",
+            Self::GMode => "G-Mode",
+            Self::Manual => "Manual",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.as_str() == name)
+            .ok_or_else(|| anyhow!("Unknown power mode: '{}'", name))
+    }
+}
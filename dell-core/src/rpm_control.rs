@@ -0,0 +1,401 @@
+// Closed-loop fan speed control
+// The same boost value spins the fans at different speeds on every model,
+// so a target RPM can't be set directly. A PI controller per fan adjusts
+// the boost from the measured RPM until the fan runs at the target.
+
+use crate::acpi::AcpiController;
+use crate::power_mode::PowerMode;
+use crate::wmax::FanId;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Proportional-integral controller with its output clamped to
+/// `[min, max]`. The integral is clamped to the same range so it can't
+/// wind up while the output is saturated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiController {
+    pub kp: f64,
+    pub ki: f64,
+    pub min: f64,
+    pub max: f64,
+    integral: f64,
+}
+
+impl PiController {
+    pub fn new(kp: f64, ki: f64, min: f64, max: f64) -> Self {
+        Self {
+            kp,
+            ki,
+            min,
+            max,
+            integral: min,
+        }
+    }
+
+    /// Continue from `output`, so taking over doesn't make the output jump
+    pub fn reset(&mut self, output: f64) {
+        self.integral = output.clamp(self.min, self.max);
+    }
+
+    /// New output for `error` after `dt` seconds
+    pub fn update(&mut self, error: f64, dt: f64) -> f64 {
+        self.integral = (self.integral + self.ki * error * dt).clamp(self.min, self.max);
+        (self.integral + self.kp * error).clamp(self.min, self.max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpmTargetConfig {
+    /// Target speed of the CPU fan, `None` leaves it alone
+    pub cpu_rpm: Option<u32>,
+    /// Target speed of the GPU fan, `None` leaves it alone
+    pub gpu_rpm: Option<u32>,
+    /// Boost per RPM of error
+    pub kp: f64,
+    /// Boost per RPM of error and second
+    pub ki: f64,
+    /// Errors below this are ignored so the fans don't hunt
+    pub deadband_rpm: u32,
+    pub min_boost: u8,
+    pub max_boost: u8,
+    /// Largest boost change per poll
+    pub max_step: u8,
+    pub poll_interval_ms: u64,
+}
+
+impl Default for RpmTargetConfig {
+    fn default() -> Self {
+        Self {
+            cpu_rpm: None,
+            gpu_rpm: None,
+            kp: 0.01,
+            ki: 0.02,
+            deadband_rpm: 75,
+            min_boost: 0,
+            max_boost: 255,
+            max_step: 24,
+            poll_interval_ms: 1000,
+        }
+    }
+}
+
+impl RpmTargetConfig {
+    pub fn new(cpu_rpm: Option<u32>, gpu_rpm: Option<u32>) -> Self {
+        Self {
+            cpu_rpm,
+            gpu_rpm,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.kp < 0.0 || self.ki < 0.0 {
+            return Err(anyhow!("PI gains can't be negative"));
+        }
+        if self.min_boost > self.max_boost {
+            return Err(anyhow!("min_boost is above max_boost"));
+        }
+        if self.max_step == 0 {
+            return Err(anyhow!("max_step must be at least 1"));
+        }
+        if self.poll_interval_ms < 100 {
+            return Err(anyhow!("poll_interval_ms must be at least 100"));
+        }
+        Ok(())
+    }
+
+    pub fn targets(&self) -> [(FanId, Option<u32>); 2] {
+        [(FanId::Fan1, self.cpu_rpm), (FanId::Fan2, self.gpu_rpm)]
+    }
+}
+
+/// Per-fan loop state between polls
+#[derive(Debug)]
+struct FanLoop {
+    pi: PiController,
+    /// Last boost written (or read back at start)
+    boost: u8,
+}
+
+impl FanLoop {
+    fn new(config: &RpmTargetConfig, boost: u8) -> Self {
+        let mut pi = PiController::new(
+            config.kp,
+            config.ki,
+            config.min_boost as f64,
+            config.max_boost as f64,
+        );
+        pi.reset(boost as f64);
+        Self { pi, boost }
+    }
+
+    /// Boost to write for a fan measured at `rpm`, if it should change
+    fn update(&mut self, config: &RpmTargetConfig, target: u32, rpm: u32, dt: f64) -> Option<u8> {
+        self.pi.kp = config.kp;
+        self.pi.ki = config.ki;
+        self.pi.min = config.min_boost as f64;
+        self.pi.max = config.max_boost as f64;
+
+        let error = target as f64 - rpm as f64;
+        let error = if error.abs() < config.deadband_rpm as f64 {
+            0.0
+        } else {
+            error
+        };
+        let output = self.pi.update(error, dt).round() as u8;
+
+        let next = if output > self.boost {
+            self.boost.saturating_add(config.max_step).min(output)
+        } else {
+            self.boost.saturating_sub(config.max_step).max(output)
+        };
+        (next != self.boost).then_some(next)
+    }
+}
+
+/// Background thread holding fans at target speeds; stops when dropped
+pub struct RpmController {
+    config: Arc<Mutex<RpmTargetConfig>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RpmController {
+    /// Switch to Manual mode (the EC ignores boost otherwise) and start
+    /// steering the fans towards their targets
+    pub fn start(acpi: Arc<Mutex<Option<AcpiController>>>, config: RpmTargetConfig) -> Self {
+        let config = Arc::new(Mutex::new(config));
+        let stop = Arc::new(AtomicBool::new(false));
+        let config_ref = config.clone();
+        let stop_flag = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut loops = {
+                let config = config_ref.lock().unwrap().clone();
+                let mut acpi = acpi.lock().unwrap();
+                let mut boost = |fan: FanId| {
+                    acpi.as_mut()
+                        .and_then(|c| c.get_fan_boost(fan).ok())
+                        .map_or(config.min_boost, |s| s.boost)
+                };
+                let loops = [
+                    FanLoop::new(&config, boost(FanId::Fan1)),
+                    FanLoop::new(&config, boost(FanId::Fan2)),
+                ];
+                if let Some(controller) = acpi.as_mut() {
+                    if let Err(e) = controller.set_power_mode(PowerMode::Manual) {
+                        warn!("RPM control: failed to switch to Manual mode: {}", e);
+                    }
+                }
+                loops
+            };
+            info!("RPM controller started");
+
+            let mut last_tick = Instant::now();
+            while !stop_flag.load(Ordering::Relaxed) {
+                let config = config_ref.lock().unwrap().clone();
+                std::thread::park_timeout(Duration::from_millis(config.poll_interval_ms));
+                if stop_flag.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let now = Instant::now();
+                let dt = now.duration_since(last_tick).as_secs_f64();
+                last_tick = now;
+                if let Err(e) = Self::tick(&acpi, &config, &mut loops, dt) {
+                    warn!("RPM control update failed: {}", e);
+                }
+            }
+            debug!("RPM controller stopped");
+        });
+
+        Self {
+            config,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn tick(
+        acpi: &Mutex<Option<AcpiController>>,
+        config: &RpmTargetConfig,
+        loops: &mut [FanLoop; 2],
+        dt: f64,
+    ) -> Result<()> {
+        let mut acpi = acpi.lock().unwrap();
+        let Some(controller) = acpi.as_mut() else {
+            return Ok(());
+        };
+
+        let (fan1, fan2) = controller.read_fan_rpms()?;
+        for (((fan, target), rpm), fan_loop) in config
+            .targets()
+            .into_iter()
+            .zip([fan1, fan2])
+            .zip(loops.iter_mut())
+        {
+            let (Some(target), Some(rpm)) = (target, rpm) else {
+                continue;
+            };
+            if let Some(boost) = fan_loop.update(config, target, rpm, dt) {
+                debug!(
+                    "RPM control: {:?} at {} RPM (target {}) -> boost {}",
+                    fan, rpm, target, boost
+                );
+                // A failed write is retried next tick and doesn't hold up
+                // the other fan
+                match controller.set_fan_boost(fan, boost) {
+                    Ok(()) => fan_loop.boost = boost,
+                    Err(e) => warn!("RPM control: {}", e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn config(&self) -> RpmTargetConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Change the targets or gains; the loops carry on from their
+    /// current boost
+    pub fn set_config(&self, config: RpmTargetConfig) -> Result<()> {
+        config.validate()?;
+        *self.config.lock().unwrap() = config;
+        Ok(())
+    }
+}
+
+impl Drop for RpmController {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acpi::INTEL_ACPI_PATH;
+    use crate::acpi_backend::FakeAcpiBackend;
+    use crate::thermal_restore::RestoreTarget;
+    use crate::wmax::WmaxCommand;
+
+    /// Fan that settles towards 20 RPM per boost step, halfway each second
+    struct Plant {
+        rpm: f64,
+    }
+
+    impl Plant {
+        fn step(&mut self, boost: u8) -> u32 {
+            self.rpm += (boost as f64 * 20.0 - self.rpm) * 0.5;
+            self.rpm.round() as u32
+        }
+    }
+
+    #[test]
+    fn output_and_integral_stay_within_limits() {
+        let mut pi = PiController::new(0.01, 0.02, 0.0, 255.0);
+        for _ in 0..100 {
+            assert_eq!(pi.update(10_000.0, 1.0), 255.0);
+        }
+        assert_eq!(pi.integral, 255.0);
+        for _ in 0..100 {
+            assert_eq!(pi.update(-10_000.0, 1.0), 0.0);
+        }
+        assert_eq!(pi.integral, 0.0);
+    }
+
+    #[test]
+    fn saturation_does_not_wind_up() {
+        let mut pi = PiController::new(0.01, 0.02, 0.0, 255.0);
+        // Fan that can't reach its target for a long time
+        for _ in 0..600 {
+            pi.update(2_000.0, 1.0);
+        }
+        // As soon as it overshoots the output comes off the limit
+        assert!(pi.update(-500.0, 1.0) < 250.0);
+    }
+
+    #[test]
+    fn reset_continues_from_the_current_boost() {
+        let mut pi = PiController::new(0.01, 0.02, 0.0, 255.0);
+        pi.reset(128.0);
+        assert_eq!(pi.update(0.0, 1.0), 128.0);
+        pi.reset(400.0);
+        assert_eq!(pi.update(0.0, 1.0), 255.0);
+    }
+
+    #[test]
+    fn converges_on_a_simulated_fan() {
+        let config = RpmTargetConfig::new(Some(3000), None);
+        let mut fan = FanLoop::new(&config, 0);
+        let mut plant = Plant { rpm: 0.0 };
+        let mut rpm = plant.step(fan.boost);
+
+        for _ in 0..60 {
+            if let Some(boost) = fan.update(&config, 3000, rpm, 1.0) {
+                assert!(boost.abs_diff(fan.boost) <= config.max_step);
+                fan.boost = boost;
+            }
+            rpm = plant.step(fan.boost);
+        }
+        assert!(rpm.abs_diff(3000) <= config.deadband_rpm, "{} RPM", rpm);
+
+        // Settled: no more writes
+        for _ in 0..10 {
+            assert_eq!(fan.update(&config, 3000, rpm, 1.0), None);
+            rpm = plant.step(fan.boost);
+        }
+    }
+
+    #[test]
+    fn a_failed_write_does_not_skip_the_other_fan() {
+        let fake = FakeAcpiBackend::new();
+        let rpm = WmaxCommand::GetFanRpm;
+        fake.respond(rpm(FanId::Fan1).method(), rpm(FanId::Fan1).args(), "0x3e8");
+        fake.respond(rpm(FanId::Fan2).method(), rpm(FanId::Fan2).args(), "0x3e8");
+        for boost in 0..=255 {
+            let cmd = WmaxCommand::SetFanBoost(FanId::Fan1, boost);
+            fake.fail(cmd.method(), cmd.args(), "Not authorized");
+        }
+        let target = RestoreTarget {
+            acpi_path: INTEL_ACPI_PATH.to_string(),
+            power_modes: vec![PowerMode::Balanced, PowerMode::Manual],
+        };
+        let acpi = Mutex::new(Some(AcpiController::for_restore(
+            Box::new(fake.clone()),
+            &target,
+            None,
+        )));
+
+        let config = RpmTargetConfig::new(Some(3000), Some(3000));
+        let mut loops = [FanLoop::new(&config, 100), FanLoop::new(&config, 100)];
+        RpmController::tick(&acpi, &config, &mut loops, 1.0).unwrap();
+
+        // Fan1 keeps its old boost so the next tick retries from there
+        assert_eq!(loops[0].boost, 100);
+        assert!(loops[1].boost > 100);
+        let written: Vec<WmaxCommand> = fake
+            .calls()
+            .iter()
+            .filter_map(|c| WmaxCommand::decode(c.method, c.args))
+            .filter(|c| matches!(c, WmaxCommand::SetFanBoost(..)))
+            .collect();
+        assert_eq!(
+            written,
+            [
+                WmaxCommand::SetFanBoost(FanId::Fan1, loops[1].boost),
+                WmaxCommand::SetFanBoost(FanId::Fan2, loops[1].boost),
+            ]
+        );
+    }
+}
@@ -0,0 +1,420 @@
+// Lighting scenes: a complete, shareable keyboard lighting setup
+//
+// A scene is stored as JSON or TOML (picked by file extension). Version 1:
+//
+//   version = 1                # required, SCENE_VERSION
+//   name = "Synthwave"         # optional
+//   brightness = 100           # optional, 0-100 (default 100)
+//
+//   [[ac_charged.zones]]       # required: lighting on AC, battery full
+//   zones = [0, 1, 2]          # zone indices (0-3 keyboard, 4+ logo/lightbar)
+//   effect = "static"          # off | static | pulse | morph
+//   color = [255, 0, 128]
+//
+//   [[ac_charged.zones]]
+//   zones = [3]
+//   effect = "morph"
+//   colors = [[255, 0, 0], [0, 0, 255]]
+//   duration = 2000
+//
+// Optional power states: `ac_charging` (defaults to `ac_charged`), `dc_on`
+// (defaults to `ac_charged` at half brightness), `ac_sleep` and `dc_sleep`
+// (default off) and `dc_low` (defaults to the red battery-low flash).
+// A `pulse` entry takes `color` and `tempo`.
+
+use crate::elc::Action;
+use crate::elc_constants::*;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const SCENE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum Effect {
+    Off,
+    Static { color: [u8; 3] },
+    Pulse { color: [u8; 3], tempo: u16 },
+    Morph { colors: Vec<[u8; 3]>, duration: u16 },
+}
+
+impl Effect {
+    pub fn actions(&self) -> Vec<Action> {
+        match self {
+            Self::Off => vec![Action::new(COLOR, DURATION_MAX, TEMPO_MIN, 0, 0, 0)],
+            Self::Static { color } => vec![Action::new(
+                COLOR,
+                DURATION_MAX,
+                TEMPO_MIN,
+                color[0],
+                color[1],
+                color[2],
+            )],
+            Self::Pulse { color, tempo } => vec![Action::new(
+                PULSE,
+                DURATION_MAX,
+                (*tempo).max(TEMPO_MIN),
+                color[0],
+                color[1],
+                color[2],
+            )],
+            Self::Morph { colors, duration } => colors
+                .iter()
+                .map(|c| Action::new(MORPH, *duration, TEMPO_MIN, c[0], c[1], c[2]))
+                .collect(),
+        }
+    }
+
+    fn scaled(&self, num: u8, den: u8) -> Self {
+        let scale = |c: &[u8; 3]| c.map(|v| (v as u16 * num as u16 / den as u16) as u8);
+        match self {
+            Self::Off => Self::Off,
+            Self::Static { color } => Self::Static {
+                color: scale(color),
+            },
+            Self::Pulse { color, tempo } => Self::Pulse {
+                color: scale(color),
+                tempo: *tempo,
+            },
+            Self::Morph { colors, duration } => Self::Morph {
+                colors: colors.iter().map(scale).collect(),
+                duration: *duration,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneEffect {
+    pub zones: Vec<u8>,
+    #[serde(flatten)]
+    pub effect: Effect,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lighting {
+    pub zones: Vec<ZoneEffect>,
+}
+
+impl Lighting {
+    pub fn all(effect: Effect) -> Self {
+        Self {
+            zones: vec![ZoneEffect {
+                zones: ZONES_ALL.to_vec(),
+                effect,
+            }],
+        }
+    }
+
+    pub fn per_zone(effects: [Effect; 4]) -> Self {
+        Self {
+            zones: effects
+                .into_iter()
+                .enumerate()
+                .map(|(zone, effect)| ZoneEffect {
+                    zones: vec![zone as u8],
+                    effect,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn off() -> Self {
+        Self::all(Effect::Off)
+    }
+
+    fn half(&self) -> Self {
+        Self {
+            zones: self
+                .zones
+                .iter()
+                .map(|z| ZoneEffect {
+                    zones: z.zones.clone(),
+                    effect: z.effect.scaled(1, 2),
+                })
+                .collect(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.zones.is_empty() {
+            return Err(anyhow!("Lighting needs at least one zone entry"));
+        }
+        for entry in &self.zones {
+            if entry.zones.is_empty() || entry.zones.iter().any(|z| *z >= MAX_ZONES) {
+                return Err(anyhow!(
+                    "Invalid zones {:?} (expected 0-{})",
+                    entry.zones,
+                    MAX_ZONES - 1
+                ));
+            }
+            if let Effect::Morph { colors, .. } = &entry.effect {
+                if colors.is_empty() {
+                    return Err(anyhow!("Morph effect needs at least one color"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default = "default_brightness")]
+    pub brightness: u8,
+    pub ac_charged: Lighting,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ac_charging: Option<Lighting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dc_on: Option<Lighting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ac_sleep: Option<Lighting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dc_sleep: Option<Lighting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dc_low: Option<Lighting>,
+}
+
+fn default_brightness() -> u8 {
+    100
+}
+
+impl Scene {
+    /// Scene with the usual defaults for the other power states
+    pub fn new(ac_charged: Lighting) -> Self {
+        Self {
+            version: SCENE_VERSION,
+            name: None,
+            brightness: default_brightness(),
+            ac_charged,
+            ac_charging: None,
+            dc_on: None,
+            ac_sleep: None,
+            dc_sleep: None,
+            dc_low: None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.version != SCENE_VERSION {
+            return Err(anyhow!(
+                "Unsupported scene version {} (expected {})",
+                self.version,
+                SCENE_VERSION
+            ));
+        }
+        if self.brightness > 100 {
+            return Err(anyhow!("Brightness must be 0-100"));
+        }
+        self.ac_charged.validate()?;
+        for lighting in [
+            &self.ac_charging,
+            &self.dc_on,
+            &self.ac_sleep,
+            &self.dc_sleep,
+            &self.dc_low,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            lighting.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Lighting for every power animation, with defaults filled in.
+    /// DC_LOW is only included when the scene overrides the battery flash.
+    pub fn resolved(&self) -> Vec<(u16, Lighting)> {
+        let mut states = vec![
+            (
+                AC_SLEEP,
+                self.ac_sleep.clone().unwrap_or_else(Lighting::off),
+            ),
+            (AC_CHARGED, self.ac_charged.clone()),
+            (
+                AC_CHARGING,
+                self.ac_charging
+                    .clone()
+                    .unwrap_or_else(|| self.ac_charged.clone()),
+            ),
+            (
+                DC_SLEEP,
+                self.dc_sleep.clone().unwrap_or_else(Lighting::off),
+            ),
+            (
+                DC_ON,
+                self.dc_on.clone().unwrap_or_else(|| self.ac_charged.half()),
+            ),
+        ];
+        if let Some(dc_low) = &self.dc_low {
+            states.push((DC_LOW, dc_low.clone()));
+        }
+        states
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scene {}", path.display()))?;
+        let scene: Self = if is_toml(path) {
+            toml::from_str(&content)?
+        } else {
+            serde_json::from_str(&content)?
+        };
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = if is_toml(path) {
+            toml::to_string(self)?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write scene {}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Scene {
+        Scene {
+            name: Some("Synthwave".into()),
+            brightness: 80,
+            dc_low: Some(Lighting::all(Effect::Pulse {
+                color: [255, 0, 0],
+                tempo: 100,
+            })),
+            ..Scene::new(Lighting {
+                zones: vec![
+                    ZoneEffect {
+                        zones: vec![0, 1, 2],
+                        effect: Effect::Static {
+                            color: [255, 0, 128],
+                        },
+                    },
+                    ZoneEffect {
+                        zones: vec![3],
+                        effect: Effect::Morph {
+                            colors: vec![[255, 0, 0], [0, 0, 255]],
+                            duration: 2000,
+                        },
+                    },
+                    ZoneEffect {
+                        zones: vec![4],
+                        effect: Effect::Off,
+                    },
+                ],
+            })
+        }
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut scene = sample();
+        assert!(scene.validate().is_ok());
+        scene.version = 2;
+        assert!(scene.validate().is_err());
+        scene.version = 0;
+        assert!(scene.validate().is_err());
+    }
+
+    #[test]
+    fn power_states_default_from_ac_charged() {
+        let scene = Scene::new(Lighting::all(Effect::Static {
+            color: [200, 100, 50],
+        }));
+        let resolved = scene.resolved();
+        let states: Vec<u16> = resolved.iter().map(|(state, _)| *state).collect();
+        assert_eq!(states, [AC_SLEEP, AC_CHARGED, AC_CHARGING, DC_SLEEP, DC_ON]);
+
+        let lighting = |state| &resolved.iter().find(|(s, _)| *s == state).unwrap().1;
+        assert_eq!(lighting(AC_SLEEP), &Lighting::off());
+        assert_eq!(lighting(DC_SLEEP), &Lighting::off());
+        assert_eq!(lighting(AC_CHARGING), &scene.ac_charged);
+        assert_eq!(
+            lighting(DC_ON),
+            &Lighting::all(Effect::Static {
+                color: [100, 50, 25],
+            })
+        );
+    }
+
+    #[test]
+    fn overridden_power_states_are_kept() {
+        let scene = sample();
+        let resolved = scene.resolved();
+        assert_eq!(resolved.len(), 6);
+        assert_eq!(resolved[5], (DC_LOW, scene.dc_low.clone().unwrap()));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let scene = sample();
+        let json = serde_json::to_string_pretty(&scene).unwrap();
+        assert_eq!(serde_json::from_str::<Scene>(&json).unwrap(), scene);
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let scene = sample();
+        let toml = toml::to_string(&scene).unwrap();
+        assert_eq!(toml::from_str::<Scene>(&toml).unwrap(), scene);
+    }
+
+    #[test]
+    fn documented_toml_layout() {
+        let scene: Scene = toml::from_str(
+            r#"
+            version = 1
+            name = "Synthwave"
+
+            [[ac_charged.zones]]
+            zones = [0, 1, 2]
+            effect = "static"
+            color = [255, 0, 128]
+
+            [[ac_charged.zones]]
+            zones = [3]
+            effect = "morph"
+            colors = [[255, 0, 0], [0, 0, 255]]
+            duration = 2000
+            "#,
+        )
+        .unwrap();
+        assert!(scene.validate().is_ok());
+        assert_eq!(scene.brightness, 100);
+        assert_eq!(scene.ac_charged.zones.len(), 2);
+        assert_eq!(
+            scene.ac_charged.zones[1].effect,
+            Effect::Morph {
+                colors: vec![[255, 0, 0], [0, 0, 255]],
+                duration: 2000,
+            }
+        );
+    }
+
+    #[test]
+    fn save_and_load_by_extension() {
+        let dir = std::env::temp_dir().join(format!("dell-scene-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let scene = sample();
+        for file in ["scene.json", "scene.toml"] {
+            let path = dir.join(file);
+            scene.save(&path).unwrap();
+            assert_eq!(Scene::load(&path).unwrap(), scene, "{}", file);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,395 @@
+// Temperature and fan sensors from every source we can read
+// WMAX readings come from AcpiController; hwmon drivers (coretemp,
+// k10temp, dell_smm, nvme, amdgpu) fill in whatever WMAX can't report.
+
+use crate::acpi::SensorReadings;
+use crate::hwmon::HwmonDevice;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// hwmon drivers offered as sensor sources
+pub const HWMON_DRIVERS: [&str; 5] = ["coretemp", "k10temp", "dell_smm", "nvme", "amdgpu"];
+
+/// Source tag for readings that came from WMAX
+pub const WMAX_SOURCE: &str = "wmax";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorKind {
+    Temperature,
+    Fan,
+}
+
+impl SensorKind {
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Temperature => "°C",
+            Self::Fan => "RPM",
+        }
+    }
+
+    fn hwmon_prefix(&self) -> &'static str {
+        match self {
+            Self::Temperature => "temp",
+            Self::Fan => "fan",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorReading {
+    /// Identifier, e.g. `wmax:cpu_temp` or `coretemp:hwmon4:temp1`
+    pub id: String,
+    pub label: String,
+    pub kind: SensorKind,
+    pub value: f64,
+    pub unit: String,
+    /// `wmax` or the hwmon driver name
+    pub source: String,
+}
+
+impl SensorReading {
+    pub fn new(id: String, label: String, kind: SensorKind, value: f64, source: &str) -> Self {
+        Self {
+            id,
+            label,
+            kind,
+            value,
+            unit: kind.unit().to_string(),
+            source: source.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HwmonChannel {
+    device: HwmonDevice,
+    kind: SensorKind,
+    channel: u32,
+    label: String,
+}
+
+/// hwmon channels found once at startup and read on every poll
+#[derive(Debug, Clone, Default)]
+pub struct HwmonSensors {
+    channels: Vec<HwmonChannel>,
+}
+
+impl HwmonSensors {
+    pub fn discover(sysfs_root: &Path) -> Self {
+        let mut channels = Vec::new();
+
+        for device in HwmonDevice::all(sysfs_root) {
+            if !HWMON_DRIVERS.contains(&device.name.as_str()) {
+                continue;
+            }
+            for kind in [SensorKind::Temperature, SensorKind::Fan] {
+                let prefix = kind.hwmon_prefix();
+                for channel in device.channels(prefix) {
+                    let label = device
+                        .label(prefix, channel)
+                        .unwrap_or_else(|| format!("{} {}{}", device.name, prefix, channel));
+                    channels.push(HwmonChannel {
+                        device: device.clone(),
+                        kind,
+                        channel,
+                        label,
+                    });
+                }
+            }
+        }
+
+        info!("hwmon sensors found: {}", channels.len());
+        Self { channels }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    pub fn read_all(&self) -> Vec<SensorReading> {
+        self.channels
+            .iter()
+            .filter_map(|c| {
+                let raw = c.device.input(c.kind.hwmon_prefix(), c.channel)?;
+                let value = match c.kind {
+                    // hwmon reports millidegrees
+                    SensorKind::Temperature => raw as f64 / 1000.0,
+                    SensorKind::Fan => raw as f64,
+                };
+                // Several devices can share a driver name (e.g. two NVMe
+                // drives), so the id includes the hwmonN directory
+                let device_id = c.device.path.file_name()?.to_string_lossy();
+                Some(SensorReading::new(
+                    format!(
+                        "{}:{}:{}{}",
+                        c.device.name,
+                        device_id,
+                        c.kind.hwmon_prefix(),
+                        c.channel
+                    ),
+                    c.label.clone(),
+                    c.kind,
+                    value,
+                    &c.device.name,
+                ))
+            })
+            .collect()
+    }
+}
+
+fn find<'a>(
+    readings: &'a [SensorReading],
+    kind: SensorKind,
+    sources: &[&str],
+    labels: &[&str],
+) -> Option<&'a SensorReading> {
+    sources.iter().find_map(|source| {
+        readings.iter().find(|r| {
+            r.kind == kind
+                && r.source == *source
+                && (labels.is_empty() || labels.iter().any(|l| r.label.to_lowercase().contains(l)))
+        })
+    })
+}
+
+impl SensorReadings {
+    /// Fill readings WMAX didn't provide from hwmon: CPU package or Tctl
+    /// temperature, amdgpu edge temperature and dell_smm fans
+    pub fn fill_missing(&mut self, hwmon: &[SensorReading]) {
+        let value = |r: &SensorReading| r.value.round().max(0.0) as u32;
+
+        if self.cpu_temp.is_none() {
+            self.cpu_temp = find(
+                hwmon,
+                SensorKind::Temperature,
+                &["coretemp", "k10temp"],
+                &["package", "tctl", "tdie"],
+            )
+            .or_else(|| {
+                find(
+                    hwmon,
+                    SensorKind::Temperature,
+                    &["coretemp", "k10temp"],
+                    &[],
+                )
+            })
+            .or_else(|| find(hwmon, SensorKind::Temperature, &["dell_smm"], &["cpu"]))
+            .map(value);
+        }
+        if self.gpu_temp.is_none() {
+            self.gpu_temp = find(hwmon, SensorKind::Temperature, &["amdgpu"], &["edge"])
+                .or_else(|| find(hwmon, SensorKind::Temperature, &["dell_smm"], &["gpu"]))
+                .map(value);
+        }
+
+        let fans: Vec<&SensorReading> = hwmon
+            .iter()
+            .filter(|r| r.kind == SensorKind::Fan && r.source == "dell_smm")
+            .collect();
+        if self.fan1_rpm.is_none() {
+            self.fan1_rpm = fans.first().map(|r| value(r));
+        }
+        if self.fan2_rpm.is_none() {
+            self.fan2_rpm = fans.get(1).map(|r| value(r));
+        }
+    }
+
+    /// WMAX values as tagged readings, for listing next to hwmon ones
+    pub fn to_readings(&self) -> Vec<SensorReading> {
+        [
+            ("cpu_temp", "CPU", SensorKind::Temperature, self.cpu_temp),
+            ("gpu_temp", "GPU", SensorKind::Temperature, self.gpu_temp),
+            ("fan1", "CPU Fan", SensorKind::Fan, self.fan1_rpm),
+            ("fan2", "GPU Fan", SensorKind::Fan, self.fan2_rpm),
+        ]
+        .into_iter()
+        .filter_map(|(id, label, kind, value)| {
+            Some(SensorReading::new(
+                format!("{}:{}", WMAX_SOURCE, id),
+                label.to_string(),
+                kind,
+                value? as f64,
+                WMAX_SOURCE,
+            ))
+        })
+        .chain(self.extra.iter().cloned())
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// hwmon tree under a temp dir, removed on drop
+    struct FakeHwmon {
+        root: PathBuf,
+    }
+
+    impl FakeHwmon {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("dell-hwmon-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            Self { root }
+        }
+
+        fn device(&self, dir: &str, driver: &str, attrs: &[(&str, &str)]) {
+            let path = self.root.join("class/hwmon").join(dir);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("name"), format!("{}\n", driver)).unwrap();
+            for (attr, value) in attrs {
+                fs::write(path.join(attr), format!("{}\n", value)).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeHwmon {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn laptop(name: &str) -> FakeHwmon {
+        let hwmon = FakeHwmon::new(name);
+        hwmon.device("hwmon0", "acpitz", &[("temp1_input", "50000")]);
+        hwmon.device(
+            "hwmon2",
+            "coretemp",
+            &[
+                ("temp1_input", "72500"),
+                ("temp1_label", "Package id 0"),
+                ("temp2_input", "68000"),
+                ("temp2_label", "Core 0"),
+            ],
+        );
+        hwmon.device(
+            "hwmon3",
+            "dell_smm",
+            &[
+                ("fan1_input", "2400"),
+                ("fan2_input", "2650"),
+                ("temp1_input", "61000"),
+            ],
+        );
+        hwmon.device("hwmon4", "nvme", &[("temp1_input", "41850")]);
+        hwmon.device("hwmon5", "nvme", &[("temp1_input", "39000")]);
+        hwmon
+    }
+
+    fn reading<'a>(readings: &'a [SensorReading], id: &str) -> &'a SensorReading {
+        readings
+            .iter()
+            .find(|r| r.id == id)
+            .unwrap_or_else(|| panic!("no reading {}", id))
+    }
+
+    #[test]
+    fn discovers_known_drivers_only() {
+        let hwmon = laptop("discover");
+        let sensors = HwmonSensors::discover(&hwmon.root);
+        let readings = sensors.read_all();
+
+        assert_eq!(readings.len(), 7);
+        assert!(readings.iter().all(|r| r.source != "acpitz"));
+
+        let package = reading(&readings, "coretemp:hwmon2:temp1");
+        assert_eq!(package.label, "Package id 0");
+        assert_eq!(package.value, 72.5);
+        assert_eq!(package.unit, "°C");
+
+        let fan = reading(&readings, "dell_smm:hwmon3:fan2");
+        assert_eq!(fan.kind, SensorKind::Fan);
+        assert_eq!(fan.value, 2650.0);
+        // Unlabeled channels are named after the driver
+        assert_eq!(fan.label, "dell_smm fan2");
+
+        // Two devices with the same driver stay apart
+        assert_eq!(reading(&readings, "nvme:hwmon4:temp1").value, 41.85);
+        assert_eq!(reading(&readings, "nvme:hwmon5:temp1").value, 39.0);
+    }
+
+    #[test]
+    fn missing_tree_has_no_sensors() {
+        let hwmon = FakeHwmon::new("empty");
+        assert!(HwmonSensors::discover(&hwmon.root).is_empty());
+    }
+
+    #[test]
+    fn hwmon_fills_what_wmax_left_out() {
+        let hwmon = laptop("fill");
+        let readings = HwmonSensors::discover(&hwmon.root).read_all();
+
+        let mut wmax = SensorReadings {
+            gpu_temp: Some(55),
+            fan1_rpm: Some(3100),
+            ..Default::default()
+        };
+        wmax.fill_missing(&readings);
+
+        // CPU from the coretemp package, GPU and fan1 kept from WMAX,
+        // fan2 from the second dell_smm fan
+        assert_eq!(wmax.cpu_temp, Some(73));
+        assert_eq!(wmax.gpu_temp, Some(55));
+        assert_eq!(wmax.fan1_rpm, Some(3100));
+        assert_eq!(wmax.fan2_rpm, Some(2650));
+    }
+
+    #[test]
+    fn amd_temperatures_fill_in() {
+        let hwmon = FakeHwmon::new("amd");
+        hwmon.device(
+            "hwmon1",
+            "k10temp",
+            &[("temp1_input", "80250"), ("temp1_label", "Tctl")],
+        );
+        hwmon.device(
+            "hwmon6",
+            "amdgpu",
+            &[
+                ("temp1_input", "49000"),
+                ("temp1_label", "edge"),
+                ("temp2_input", "52000"),
+                ("temp2_label", "junction"),
+            ],
+        );
+        let readings = HwmonSensors::discover(&hwmon.root).read_all();
+
+        let mut wmax = SensorReadings::default();
+        wmax.fill_missing(&readings);
+        assert_eq!(wmax.cpu_temp, Some(80));
+        assert_eq!(wmax.gpu_temp, Some(49));
+        assert_eq!(wmax.fan1_rpm, None);
+        assert_eq!(wmax.fan2_rpm, None);
+    }
+
+    #[test]
+    fn wmax_readings_are_tagged() {
+        let extra = SensorReading::new(
+            "wmax:fan_0x34".to_string(),
+            "Fan 0x34".to_string(),
+            SensorKind::Fan,
+            1800.0,
+            WMAX_SOURCE,
+        );
+        let wmax = SensorReadings {
+            cpu_temp: Some(70),
+            fan2_rpm: Some(2500),
+            extra: vec![extra.clone()],
+            ..Default::default()
+        };
+
+        let readings = wmax.to_readings();
+        let ids: Vec<&str> = readings.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["wmax:cpu_temp", "wmax:fan2", "wmax:fan_0x34"]);
+        assert!(readings.iter().all(|r| r.source == WMAX_SOURCE));
+        assert_eq!(readings[0].label, "CPU");
+        assert_eq!(readings[0].value, 70.0);
+        assert_eq!(readings[1].unit, "RPM");
+        assert_eq!(readings[2], extra);
+    }
+}
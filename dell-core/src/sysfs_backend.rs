@@ -0,0 +1,408 @@
+// Backend on top of the kernel's alienware-wmi driver (Linux 6.15+)
+// Thermal modes go through platform_profile and fans/temperatures
+// through the driver's hwmon device, so acpi_call isn't needed.
+// WMAX commands are translated to the matching sysfs attributes.
+
+use crate::acpi_backend::AcpiBackend;
+use crate::hwmon::HwmonDevice;
+use crate::power_mode::PowerMode;
+use crate::wmax::{FanId, SensorId, WmaxCommand};
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::path::{Path, PathBuf};
+
+pub const SYSFS_ROOT: &str = "/sys";
+
+const PLATFORM_PROFILE: &str = "firmware/acpi/platform_profile";
+const ALIENWARE_HWMON: &str = "alienware_wmi";
+
+// acpi_call's answer for a method the firmware doesn't have
+const NOT_SUPPORTED: &str = "Error: AE_NOT_FOUND";
+// What WMAX returns for a fan or sensor that isn't present
+const UNAVAILABLE: i64 = 0xffff_ffff;
+
+#[derive(Debug)]
+pub struct SysfsBackend {
+    root: PathBuf,
+    hwmon: HwmonDevice,
+    // The driver has no separate G-Mode flag, it's emulated on top of the
+    // performance profile
+    g_mode: bool,
+}
+
+impl SysfsBackend {
+    /// `None` unless `root` has both platform_profile and the
+    /// alienware_wmi hwmon device
+    pub fn detect(root: &Path) -> Option<Self> {
+        if !root.join(PLATFORM_PROFILE).is_file() {
+            return None;
+        }
+        let hwmon = HwmonDevice::find(root, ALIENWARE_HWMON)?;
+        debug!("alienware-wmi hwmon at {}", hwmon.path.display());
+        let mut backend = Self {
+            root: root.to_path_buf(),
+            hwmon,
+            g_mode: false,
+        };
+        backend.g_mode = backend.read_profile().ok()? == G_MODE_PROFILE;
+        Some(backend)
+    }
+
+    fn profile_path(&self) -> PathBuf {
+        self.root.join(PLATFORM_PROFILE)
+    }
+
+    fn read_profile(&self) -> Result<String> {
+        let path = self.profile_path();
+        std::fs::read_to_string(&path)
+            .map(|p| p.trim().to_string())
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))
+    }
+
+    fn write_profile(&self, profile: &str) -> Result<()> {
+        let path = self.profile_path();
+        debug!("platform_profile <- {}", profile);
+        std::fs::write(&path, profile)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// G-Mode is on while the flag is set and nobody moved the profile
+    /// away from performance
+    fn g_mode_enabled(&self) -> Result<bool> {
+        Ok(self.g_mode && self.read_profile()? == G_MODE_PROFILE)
+    }
+
+    fn toggle_g_mode(&mut self) -> Result<()> {
+        self.g_mode = !self.g_mode_enabled()?;
+        if self.g_mode {
+            self.write_profile(G_MODE_PROFILE)
+        } else if self.read_profile()? == G_MODE_PROFILE {
+            self.write_profile("balanced")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn fan_channel(fan: FanId) -> u32 {
+        // The driver registers fans in WMAX fan ID order
+        fan.index() as u32
+    }
+
+    fn temp_channel(&self, sensor: SensorId) -> Option<u32> {
+        let (needle, fallback) = match sensor {
+            SensorId::Cpu => ("cpu", 1),
+            SensorId::Gpu => ("gpu", 2),
+            // No way to tell which hwmon channel an arbitrary ID maps to
+            SensorId::Other(_) => return None,
+        };
+        self.hwmon.channel_labeled("temp", needle).or_else(|| {
+            self.hwmon
+                .channels("temp")
+                .contains(&fallback)
+                .then_some(fallback)
+        })
+    }
+
+    /// Integer result of `cmd`, `None` when sysfs has no equivalent
+    fn execute(&mut self, cmd: WmaxCommand) -> Result<Option<u64>> {
+        let value = match cmd {
+            WmaxCommand::GetPowerMode => {
+                profile_to_mode(&self.read_profile()?).map(|m| m.code() as i64)
+            }
+            WmaxCommand::SetPowerMode(code) => {
+                let profile = PowerMode::from_code(code)
+                    .and_then(mode_to_profile)
+                    .ok_or_else(|| anyhow!("Mode 0x{:02x} has no platform_profile", code))?;
+                self.write_profile(profile)?;
+                Some(0)
+            }
+            WmaxCommand::ToggleGMode => {
+                self.toggle_g_mode()?;
+                Some(0)
+            }
+            WmaxCommand::GetGMode => Some(self.g_mode_enabled()? as i64),
+            WmaxCommand::GetFanRpm(fan) => Some(
+                self.hwmon
+                    .input("fan", Self::fan_channel(fan))
+                    .unwrap_or(UNAVAILABLE),
+            ),
+            WmaxCommand::GetTemp(sensor) => Some(
+                self.temp_channel(sensor)
+                    .and_then(|c| self.hwmon.input("temp", c))
+                    // hwmon reports millidegrees
+                    .map(|t| t / 1000)
+                    .unwrap_or(UNAVAILABLE),
+            ),
+            WmaxCommand::GetFanBoost(fan) => self
+                .hwmon
+                .read_value(&format!("fan{}_boost", Self::fan_channel(fan))),
+            WmaxCommand::SetFanBoost(fan, boost) => {
+                self.hwmon.write_attr(
+                    &format!("fan{}_boost", Self::fan_channel(fan)),
+                    &boost.to_string(),
+                )?;
+                Some(0)
+            }
+            WmaxCommand::GetLaptopModel => None,
+        };
+        Ok(value.map(|v| v.max(0) as u64))
+    }
+}
+
+// On G-series the driver backs "performance" with G-Mode
+const G_MODE_PROFILE: &str = "performance";
+
+/// platform_profile choice for a thermal mode, as mapped by alienware-wmi.
+/// Full Speed has no profile of its own ("performance" is G-Mode), so it
+/// isn't available through this backend.
+fn mode_to_profile(mode: PowerMode) -> Option<&'static str> {
+    match mode {
+        PowerMode::Balanced => Some("balanced"),
+        PowerMode::Performance => Some("balanced-performance"),
+        PowerMode::Cool => Some("cool"),
+        PowerMode::Quiet => Some("quiet"),
+        PowerMode::GMode => Some(G_MODE_PROFILE),
+        PowerMode::FullSpeed => None,
+        PowerMode::BatterySaver => Some("low-power"),
+        PowerMode::Manual => Some("custom"),
+    }
+}
+
+fn profile_to_mode(profile: &str) -> Option<PowerMode> {
+    match profile {
+        "balanced" => Some(PowerMode::Balanced),
+        "balanced-performance" => Some(PowerMode::Performance),
+        "cool" => Some(PowerMode::Cool),
+        "quiet" => Some(PowerMode::Quiet),
+        G_MODE_PROFILE => Some(PowerMode::GMode),
+        "low-power" => Some(PowerMode::BatterySaver),
+        "custom" => Some(PowerMode::Manual),
+        _ => None,
+    }
+}
+
+impl AcpiBackend for SysfsBackend {
+    fn call(&mut self, _path: &str, method: u8, args: [u8; 4]) -> Result<String> {
+        let Some(cmd) = WmaxCommand::decode(method, args) else {
+            return Ok(NOT_SUPPORTED.to_string());
+        };
+        let result = match self.execute(cmd)? {
+            Some(value) => format!("0x{:x}", value),
+            None => NOT_SUPPORTED.to_string(),
+        };
+        debug!("sysfs [{:?}]: {}", cmd, result);
+        Ok(result)
+    }
+
+    fn supports_mode(&self, mode: PowerMode) -> bool {
+        mode_to_profile(mode).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acpi::{AcpiController, INTEL_ACPI_PATH};
+    use crate::hardware::HardwareInfo;
+    use crate::models::ModelDatabase;
+    use std::fs;
+
+    /// Minimal alienware-wmi tree under a temp dir, removed on drop
+    struct FakeSysfs {
+        root: PathBuf,
+    }
+
+    impl FakeSysfs {
+        fn new(name: &str, profile: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("dell-sysfs-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            let hwmon = root.join("class/hwmon/hwmon3");
+            fs::create_dir_all(root.join("firmware/acpi")).unwrap();
+            fs::create_dir_all(&hwmon).unwrap();
+            fs::create_dir_all(root.join("class/hwmon/hwmon0")).unwrap();
+            fs::write(root.join("class/hwmon/hwmon0/name"), "acpitz\n").unwrap();
+            fs::write(root.join(PLATFORM_PROFILE), format!("{}\n", profile)).unwrap();
+            for (attr, value) in [
+                ("name", ALIENWARE_HWMON),
+                ("fan1_input", "2400"),
+                ("fan2_input", "2600"),
+                ("fan1_boost", "0"),
+                ("fan2_boost", "0"),
+                ("temp1_input", "71000"),
+                ("temp1_label", "CPU Proximity"),
+                ("temp2_input", "58000"),
+                ("temp2_label", "GPU Proximity"),
+            ] {
+                fs::write(hwmon.join(attr), format!("{}\n", value)).unwrap();
+            }
+            Self { root }
+        }
+
+        fn backend(&self) -> SysfsBackend {
+            SysfsBackend::detect(&self.root).expect("fake tree not detected")
+        }
+
+        fn profile(&self) -> String {
+            fs::read_to_string(self.root.join(PLATFORM_PROFILE))
+                .unwrap()
+                .trim()
+                .to_string()
+        }
+
+        fn set_profile(&self, profile: &str) {
+            fs::write(self.root.join(PLATFORM_PROFILE), profile).unwrap();
+        }
+
+        fn controller(&self) -> AcpiController {
+            self.controller_for("Dell G15 5530")
+        }
+
+        fn controller_for(&self, product: &str) -> AcpiController {
+            let hardware = HardwareInfo {
+                sys_vendor: "Dell Inc.".to_string(),
+                product_name: product.to_string(),
+                cpu_vendor: "GenuineIntel".to_string(),
+                ..Default::default()
+            };
+            AcpiController::with_models(
+                Box::new(self.backend()),
+                ModelDatabase::embedded(),
+                hardware,
+            )
+            .unwrap()
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn call(backend: &mut SysfsBackend, cmd: WmaxCommand) -> String {
+        backend
+            .call(INTEL_ACPI_PATH, cmd.method(), cmd.args())
+            .unwrap()
+    }
+
+    #[test]
+    fn detection_needs_profile_and_hwmon() {
+        let sysfs = FakeSysfs::new("detect", "balanced");
+        assert!(SysfsBackend::detect(&sysfs.root).is_some());
+
+        fs::remove_file(sysfs.root.join(PLATFORM_PROFILE)).unwrap();
+        assert!(SysfsBackend::detect(&sysfs.root).is_none());
+
+        let sysfs = FakeSysfs::new("detect-hwmon", "balanced");
+        fs::remove_dir_all(sysfs.root.join("class/hwmon/hwmon3")).unwrap();
+        assert!(SysfsBackend::detect(&sysfs.root).is_none());
+    }
+
+    #[test]
+    fn fans_and_temperatures_come_from_hwmon() {
+        let sysfs = FakeSysfs::new("sensors", "balanced");
+        let mut backend = sysfs.backend();
+        assert_eq!(
+            call(&mut backend, WmaxCommand::GetFanRpm(FanId::Fan2)),
+            "0xa28"
+        );
+        assert_eq!(
+            call(&mut backend, WmaxCommand::GetTemp(SensorId::Cpu)),
+            "0x47"
+        );
+        assert_eq!(
+            call(&mut backend, WmaxCommand::GetTemp(SensorId::Gpu)),
+            "0x3a"
+        );
+        assert_eq!(
+            call(&mut backend, WmaxCommand::GetFanRpm(FanId::Extra(0x34))),
+            "0xffffffff"
+        );
+        assert_eq!(
+            call(&mut backend, WmaxCommand::GetLaptopModel),
+            NOT_SUPPORTED
+        );
+
+        call(&mut backend, WmaxCommand::SetFanBoost(FanId::Fan1, 128));
+        let boost = fs::read_to_string(sysfs.root.join("class/hwmon/hwmon3/fan1_boost")).unwrap();
+        assert_eq!(boost, "128");
+    }
+
+    #[test]
+    fn power_mode_round_trips_through_platform_profile() {
+        let sysfs = FakeSysfs::new("modes", "balanced");
+        let mut controller = sysfs.controller();
+        for mode in [
+            PowerMode::Performance,
+            PowerMode::Quiet,
+            PowerMode::Manual,
+            PowerMode::Balanced,
+        ] {
+            controller.set_power_mode(mode).unwrap();
+            assert_eq!(controller.current_power_mode().unwrap(), mode);
+        }
+        assert_eq!(sysfs.profile(), "balanced");
+    }
+
+    #[test]
+    fn full_speed_is_an_error() {
+        let sysfs = FakeSysfs::new("full-speed", "balanced");
+        let mut backend = sysfs.backend();
+        let cmd = WmaxCommand::SetPowerMode(PowerMode::FullSpeed.code());
+        assert!(backend
+            .call(INTEL_ACPI_PATH, cmd.method(), cmd.args())
+            .is_err());
+        assert_eq!(sysfs.profile(), "balanced");
+    }
+
+    #[test]
+    fn modes_without_a_profile_are_not_offered() {
+        let sysfs = FakeSysfs::new("mode-list", "balanced");
+        let controller = sysfs.controller_for("Dell G16 7620");
+        assert_eq!(controller.model.id, "g16-7620");
+        assert!(controller.model.power_modes.contains(&PowerMode::FullSpeed));
+        assert!(!controller.power_modes.contains(&PowerMode::FullSpeed));
+        assert!(controller.power_modes.contains(&PowerMode::GMode));
+
+        let controller = sysfs.controller_for("Something Else");
+        assert!(controller.model.is_unknown());
+        assert!(!controller.power_modes.contains(&PowerMode::FullSpeed));
+    }
+
+    #[test]
+    fn g_mode_on_and_off() {
+        let sysfs = FakeSysfs::new("g-mode", "quiet");
+        let mut controller = sysfs.controller();
+        assert!(!controller.g_mode_enabled().unwrap());
+
+        controller.set_g_mode(true).unwrap();
+        assert!(controller.g_mode_enabled().unwrap());
+        assert_eq!(sysfs.profile(), "performance");
+        assert_eq!(controller.current_power_mode().unwrap(), PowerMode::GMode);
+
+        controller.set_g_mode(false).unwrap();
+        assert!(!controller.g_mode_enabled().unwrap());
+        assert_ne!(sysfs.profile(), "performance");
+    }
+
+    #[test]
+    fn g_mode_follows_outside_profile_changes() {
+        let sysfs = FakeSysfs::new("g-mode-outside", "performance");
+        let mut backend = sysfs.backend();
+        assert_eq!(call(&mut backend, WmaxCommand::GetGMode), "0x1");
+
+        // e.g. powerprofilesctl switching profiles behind our back
+        sysfs.set_profile("balanced");
+        assert_eq!(call(&mut backend, WmaxCommand::GetGMode), "0x0");
+
+        // Toggling from the effective state turns it back on
+        call(&mut backend, WmaxCommand::ToggleGMode);
+        assert_eq!(call(&mut backend, WmaxCommand::GetGMode), "0x1");
+        assert_eq!(sysfs.profile(), "performance");
+
+        call(&mut backend, WmaxCommand::ToggleGMode);
+        assert_eq!(call(&mut backend, WmaxCommand::GetGMode), "0x0");
+        assert_eq!(sysfs.profile(), "balanced");
+    }
+}
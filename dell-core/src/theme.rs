@@ -0,0 +1,345 @@
+// Desktop theme import (pywal / base16) for keyboard zone colors
+// Reads the terminal palette and maps selected entries to zones 0-3
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// Palette indices used for each zone, left to right
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoneMapping(pub [usize; 4]);
+
+impl ZoneMapping {
+    /// pywal: red, green, blue, magenta (color1, color2, color4, color5)
+    pub const PYWAL: Self = Self([1, 2, 4, 5]);
+    /// base16: red, green, blue, magenta (base08, base0B, base0D, base0E)
+    pub const BASE16: Self = Self([0x08, 0x0b, 0x0d, 0x0e]);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeFormat {
+    Pywal,
+    Base16,
+}
+
+impl ThemeFormat {
+    pub fn default_mapping(&self) -> ZoneMapping {
+        match self {
+            Self::Pywal => ZoneMapping::PYWAL,
+            Self::Base16 => ZoneMapping::BASE16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: Option<String>,
+    pub format: ThemeFormat,
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl Theme {
+    /// Load a theme file, picking the parser from the extension
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read theme file {}", path.display()))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => Self::from_base16_yaml(&content),
+            _ => Self::from_pywal_json(&content),
+        }
+    }
+
+    /// Parse pywal's `colors.json` (`colors.color0` .. `colors.color15`)
+    pub fn from_pywal_json(content: &str) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(content)?;
+        let colors = json
+            .get("colors")
+            .ok_or_else(|| anyhow!("pywal theme has no 'colors' section"))?;
+
+        let mut palette = Vec::new();
+        for i in 0..16 {
+            match colors.get(format!("color{}", i)).and_then(|v| v.as_str()) {
+                Some(hex) => palette.push(parse_hex_color(hex)?),
+                None => break,
+            }
+        }
+
+        if palette.is_empty() {
+            return Err(anyhow!("pywal theme has no colors"));
+        }
+
+        let name = json
+            .get("wallpaper")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        Ok(Self {
+            name,
+            format: ThemeFormat::Pywal,
+            colors: palette,
+        })
+    }
+
+    /// Parse a base16 scheme (`base00` .. `base0F`); also accepts the
+    /// newer tinted-theming layout where the entries sit under `palette:`
+    pub fn from_base16_yaml(content: &str) -> Result<Self> {
+        let mut palette = [None; 16];
+        let mut name = None;
+
+        for line in content.lines() {
+            let Some((key, value)) = line.trim().split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = yaml_scalar(value);
+
+            if key == "scheme" || key == "name" {
+                name = Some(value.to_string());
+            } else if let Some(idx) = key.strip_prefix("base0") {
+                if let Ok(idx) = usize::from_str_radix(idx, 16) {
+                    let entry = palette
+                        .get_mut(idx)
+                        .ok_or_else(|| anyhow!("Unknown base16 entry '{}'", key))?;
+                    *entry = Some(parse_hex_color(value)?);
+                }
+            }
+        }
+
+        let colors = palette
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.ok_or_else(|| anyhow!("base16 scheme is missing base0{:X}", i)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name,
+            format: ThemeFormat::Base16,
+            colors,
+        })
+    }
+
+    pub fn zone_colors(&self, mapping: ZoneMapping) -> Result<[[u8; 3]; 4]> {
+        let mut zones = [[0u8; 3]; 4];
+        for (zone, idx) in mapping.0.iter().enumerate() {
+            zones[zone] = *self
+                .colors
+                .get(*idx)
+                .ok_or_else(|| anyhow!("Theme has no palette entry {}", idx))?;
+        }
+        Ok(zones)
+    }
+}
+
+// Value of a simple `key: value` line, without quotes or trailing comment
+fn yaml_scalar(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(rest) = value.strip_prefix(quote) {
+            return rest.split(quote).next().unwrap_or("");
+        }
+    }
+    value.split(" #").next().unwrap_or("").trim()
+}
+
+fn parse_hex_color(value: &str) -> Result<[u8; 3]> {
+    let hex = value.trim().trim_start_matches('#');
+    // Also keeps the byte slicing below on char boundaries
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid hex color: {}", value));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// `$XDG_CACHE_HOME/wal/colors.json`, falling back to `~/.cache`
+pub fn default_pywal_path() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    Some(cache.join("wal").join("colors.json"))
+}
+
+/// Polls a theme file and calls back whenever it changes
+pub struct ThemeWatcher {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ThemeWatcher {
+    pub fn start<F>(path: PathBuf, interval: Duration, on_change: F) -> Self
+    where
+        F: Fn(&Theme) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let watched = path.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut last_modified: Option<SystemTime> = None;
+
+            while !stop_flag.load(Ordering::Relaxed) {
+                let modified = std::fs::metadata(&watched).and_then(|m| m.modified()).ok();
+
+                if modified.is_some() && modified != last_modified {
+                    match Theme::load(&watched) {
+                        Ok(theme) => {
+                            info!("Theme file changed: {}", watched.display());
+                            last_modified = modified;
+                            on_change(&theme);
+                        }
+                        // The file may be mid-write; retry on the next poll
+                        Err(e) => warn!("Failed to load theme {}: {}", watched.display(), e),
+                    }
+                }
+
+                std::thread::sleep(interval);
+            }
+            debug!("Theme watcher stopped: {}", watched.display());
+        });
+
+        Self {
+            path,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ThemeWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PYWAL: &str = r##"{
+        "wallpaper": "/home/user/Pictures/forest.jpg",
+        "special": { "background": "#0b0f10", "foreground": "#c5c8c6" },
+        "colors": {
+            "color0": "#0b0f10", "color1": "#c0392b", "color2": "#27ae60",
+            "color3": "#f1c40f", "color4": "#2980b9", "color5": "#8e44ad",
+            "color6": "#16a085", "color7": "#c5c8c6"
+        }
+    }"##;
+
+    fn base16(extra: &str) -> String {
+        let mut scheme = String::from("scheme: \"Test Scheme\"\nauthor: someone\n");
+        for i in 0..16 {
+            scheme.push_str(&format!(
+                "base0{:X}: \"{:02x}{:02x}{:02x}\" # entry\n",
+                i, i, i, i
+            ));
+        }
+        scheme.push_str(extra);
+        scheme
+    }
+
+    #[test]
+    fn pywal_colors_in_order() {
+        let theme = Theme::from_pywal_json(PYWAL).unwrap();
+        assert_eq!(theme.format, ThemeFormat::Pywal);
+        assert_eq!(
+            theme.name.as_deref(),
+            Some("/home/user/Pictures/forest.jpg")
+        );
+        assert_eq!(theme.colors.len(), 8);
+        assert_eq!(theme.colors[1], [0xc0, 0x39, 0x2b]);
+        assert_eq!(
+            theme.zone_colors(ZoneMapping::PYWAL).unwrap(),
+            [
+                [0xc0, 0x39, 0x2b],
+                [0x27, 0xae, 0x60],
+                [0x29, 0x80, 0xb9],
+                [0x8e, 0x44, 0xad],
+            ]
+        );
+    }
+
+    #[test]
+    fn pywal_errors() {
+        for bad in [
+            "not json",
+            "{}",
+            r#"{"colors": {}}"#,
+            r##"{"colors": {"color0": "#zzzzzz"}}"##,
+        ] {
+            assert!(Theme::from_pywal_json(bad).is_err(), "{}", bad);
+        }
+        // Mapping past the end of a short palette
+        let theme = Theme::from_pywal_json(r##"{"colors": {"color0": "#000000"}}"##).unwrap();
+        assert!(theme.zone_colors(ZoneMapping::PYWAL).is_err());
+    }
+
+    #[test]
+    fn base16_scheme() {
+        let theme = Theme::from_base16_yaml(&base16("")).unwrap();
+        assert_eq!(theme.format, ThemeFormat::Base16);
+        assert_eq!(theme.name.as_deref(), Some("Test Scheme"));
+        assert_eq!(theme.colors.len(), 16);
+        assert_eq!(theme.colors[0x0b], [0x0b; 3]);
+        assert_eq!(
+            theme.zone_colors(ZoneMapping::BASE16).unwrap(),
+            [[0x08; 3], [0x0b; 3], [0x0d; 3], [0x0e; 3]]
+        );
+        assert_eq!(
+            theme.zone_colors(ZoneMapping([0, 0, 15, 1])).unwrap(),
+            [[0; 3], [0; 3], [0x0f; 3], [1; 3]]
+        );
+    }
+
+    #[test]
+    fn base16_tinted_theming_layout() {
+        let mut scheme = String::from("system: \"base16\"\nname: 'Nested'\npalette:\n");
+        for i in 0..16 {
+            scheme.push_str(&format!("  base0{:x}: '#{:02x}0000'\n", i, i * 16));
+        }
+        let theme = Theme::from_base16_yaml(&scheme).unwrap();
+        assert_eq!(theme.name.as_deref(), Some("Nested"));
+        assert_eq!(theme.colors[0x0f], [0xf0, 0, 0]);
+    }
+
+    #[test]
+    fn base16_errors() {
+        // Missing entry
+        let incomplete: String = base16("")
+            .lines()
+            .filter(|l| !l.starts_with("base07"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(Theme::from_base16_yaml(&incomplete).is_err());
+        // Entries past base0F are rejected instead of indexing out of bounds
+        for extra in ["base0100: \"ffffff\"\n", "base0ff: \"ffffff\"\n"] {
+            assert!(
+                Theme::from_base16_yaml(&base16(extra)).is_err(),
+                "{}",
+                extra
+            );
+        }
+        assert!(Theme::from_base16_yaml(&base16("base0A: \"nothex\"\n")).is_err());
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex_color("#1a2B3c").unwrap(), [0x1a, 0x2b, 0x3c]);
+        assert_eq!(parse_hex_color(" ffffff ").unwrap(), [255, 255, 255]);
+        for bad in ["#fff", "#12345g", "#+fffff", "#ééé", "#éééé"] {
+            assert!(parse_hex_color(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,336 @@
+// Restore firmware thermal control on exit
+// Manual mode and its fan boost outlive the process that set them: if the
+// app quits, crashes or is killed with the fans at 0%, they stay at 0%.
+// The last automatic (USTT) mode is saved on every change so it can be put
+// back on the way out, either in-process or by a small watchdog process
+// that outlives a SIGKILL. The watchdog gets the ACPI path and modes on its
+// command line and only talks to the backend, without model detection.
+
+use crate::acpi::AcpiController;
+use crate::acpi_backend;
+use crate::config;
+use crate::power_mode::PowerMode;
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Mutex, TryLockError};
+use std::time::{Duration, Instant};
+
+const RESTORE_FILE: &str = "restore.json";
+const LAST_MODE_FILE: &str = "last_power_mode.json";
+
+/// Argument that makes the app binary run `run_exit_watchdog` instead
+pub const WATCHDOG_ARG: &str = "--restore-watchdog";
+
+/// Modes tried, in order, when neither the last mode nor the configured
+/// fallback is supported
+const FALLBACK_MODES: [PowerMode; 3] = [
+    PowerMode::Balanced,
+    PowerMode::Performance,
+    PowerMode::Quiet,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreConfig {
+    /// Leave Manual mode when the app exits
+    pub enabled: bool,
+    /// Also restore from a separate process when the app dies without
+    /// running its exit handlers (SIGKILL, OOM killer, abort)
+    pub watchdog_process: bool,
+    /// Mode used when no automatic mode has been seen yet
+    pub fallback_mode: PowerMode,
+}
+
+impl Default for RestoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            watchdog_process: false,
+            fallback_mode: PowerMode::Balanced,
+        }
+    }
+}
+
+impl RestoreConfig {
+    /// Config stored in the user's config directory, defaults if missing
+    pub fn load_default() -> Result<Self> {
+        Self::load(&config::config_file(RESTORE_FILE)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let config: Self = config::load_json(path)?.unwrap_or_default();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn save_default(&self) -> Result<()> {
+        self.save(&config::config_file(RESTORE_FILE)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        config::save_json(path, self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.fallback_mode == PowerMode::Manual {
+            return Err(anyhow!("fallback_mode can't be Manual"));
+        }
+        Ok(())
+    }
+
+    /// `last` if the model has it, then `fallback_mode`, then the first
+    /// supported mode from `FALLBACK_MODES`
+    pub fn mode_for(&self, last: Option<PowerMode>, supported: &[PowerMode]) -> PowerMode {
+        last.into_iter()
+            .chain(std::iter::once(self.fallback_mode))
+            .chain(FALLBACK_MODES)
+            .find(|m| supported.contains(m))
+            .unwrap_or(self.fallback_mode)
+    }
+}
+
+/// ACPI path and thermal modes of the running machine, passed to the
+/// watchdog process so it can restore without detecting the model again
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreTarget {
+    pub acpi_path: String,
+    pub power_modes: Vec<PowerMode>,
+}
+
+impl RestoreTarget {
+    pub fn of(controller: &AcpiController) -> Self {
+        Self {
+            acpi_path: controller.acpi_path().to_string(),
+            power_modes: controller.power_modes.clone(),
+        }
+    }
+
+    /// `<acpi_path> <mode code>,<mode code>...`
+    fn to_args(&self) -> [String; 2] {
+        let codes: Vec<String> = self
+            .power_modes
+            .iter()
+            .map(|m| m.code().to_string())
+            .collect();
+        [self.acpi_path.clone(), codes.join(",")]
+    }
+
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let [acpi_path, codes] = args else {
+            return Err(anyhow!("Expected <acpi_path> <mode codes>, got {:?}", args));
+        };
+        let power_modes = codes
+            .split(',')
+            .map(|code| {
+                code.parse()
+                    .ok()
+                    .and_then(PowerMode::from_code)
+                    .ok_or_else(|| anyhow!("Invalid power mode code '{}'", code))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            acpi_path: acpi_path.clone(),
+            power_modes,
+        })
+    }
+}
+
+/// Where `AcpiController::track_last_mode` keeps the last automatic mode
+pub fn last_mode_path() -> Result<PathBuf> {
+    config::config_file(LAST_MODE_FILE)
+}
+
+pub fn load_last_mode(path: &Path) -> Option<PowerMode> {
+    config::load_json(path).unwrap_or_else(|e| {
+        debug!("Ignoring saved power mode: {}", e);
+        None
+    })
+}
+
+pub fn save_last_mode(path: &Path, mode: PowerMode) -> Result<()> {
+    config::save_json(path, &mode)
+}
+
+/// Leave Manual mode for the last automatic mode. Returns the mode set,
+/// or `None` if the firmware already had control.
+pub fn restore_firmware_control(
+    controller: &mut AcpiController,
+    config: &RestoreConfig,
+) -> Result<Option<PowerMode>> {
+    if controller.current_power_mode()? != PowerMode::Manual {
+        return Ok(None);
+    }
+    let mode = config.mode_for(controller.last_auto_mode(), &controller.power_modes);
+    controller.set_power_mode(mode)?;
+    info!("Firmware thermal control restored: {}", mode.as_str());
+    Ok(Some(mode))
+}
+
+/// `restore_firmware_control` for exit paths, if `config` enables it.
+/// Gives up after `timeout` if the lock stays taken (a hung thread, or a
+/// panic hook running on the thread that holds it) and recovers the
+/// controller from a poisoned lock.
+pub fn restore_locked(
+    acpi: &Mutex<Option<AcpiController>>,
+    config: &RestoreConfig,
+    timeout: Duration,
+) -> Result<Option<PowerMode>> {
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let start = Instant::now();
+    let mut acpi = loop {
+        match acpi.try_lock() {
+            Ok(guard) => break guard,
+            Err(TryLockError::Poisoned(poisoned)) => break poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) if start.elapsed() >= timeout => {
+                return Err(anyhow!("ACPI controller still busy after {:?}", timeout));
+            }
+            Err(TryLockError::WouldBlock) => std::thread::sleep(Duration::from_millis(50)),
+        }
+    };
+    match acpi.as_mut() {
+        Some(controller) => restore_firmware_control(controller, config),
+        None => Ok(None),
+    }
+}
+
+/// Start the current executable as an exit watchdog. It waits for its
+/// stdin to close, which happens however this process ends, so the
+/// returned `Child` must be kept for as long as the app runs.
+pub fn spawn_exit_watchdog(target: &RestoreTarget) -> Result<Child> {
+    let exe = std::env::current_exe().context("Could not locate the app executable")?;
+    let child = Command::new(exe)
+        .arg(WATCHDOG_ARG)
+        .args(target.to_args())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        // Its own process group, so Ctrl+C in a terminal doesn't reach it
+        .process_group(0)
+        .spawn()
+        .context("Failed to start the exit watchdog")?;
+    info!("Exit watchdog started (pid {})", child.id());
+    Ok(child)
+}
+
+/// Body of the watchdog process, `args` being the ones after
+/// `WATCHDOG_ARG`: block until the app is gone, then put the firmware back
+/// in control if the app left it in Manual mode
+pub fn run_exit_watchdog(args: &[String]) -> Result<()> {
+    let target = RestoreTarget::from_args(args)?;
+    let mut buf = Vec::new();
+    if let Err(e) = std::io::stdin().read_to_end(&mut buf) {
+        debug!("Exit watchdog stdin error: {}", e);
+    }
+
+    let config = RestoreConfig::load_default()?;
+    if !config.enabled {
+        return Ok(());
+    }
+    let last = load_last_mode(&last_mode_path()?);
+    let mut controller =
+        AcpiController::for_restore(acpi_backend::default_backend(), &target, last);
+    match restore_firmware_control(&mut controller, &config)? {
+        Some(mode) => warn!("App exited in Manual mode, restored {}", mode.as_str()),
+        None => debug!("App exited outside Manual mode, nothing to restore"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acpi::INTEL_ACPI_PATH;
+    use crate::acpi_backend::FakeAcpiBackend;
+    use crate::wmax::WmaxCommand;
+
+    fn target() -> RestoreTarget {
+        RestoreTarget {
+            acpi_path: INTEL_ACPI_PATH.to_string(),
+            power_modes: vec![
+                PowerMode::Balanced,
+                PowerMode::Quiet,
+                PowerMode::GMode,
+                PowerMode::Manual,
+            ],
+        }
+    }
+
+    fn respond(fake: &FakeAcpiBackend, cmd: WmaxCommand, result: &str) {
+        fake.respond(cmd.method(), cmd.args(), result);
+    }
+
+    fn sent(fake: &FakeAcpiBackend) -> Vec<WmaxCommand> {
+        fake.calls()
+            .iter()
+            .filter_map(|c| WmaxCommand::decode(c.method, c.args))
+            .collect()
+    }
+
+    #[test]
+    fn mode_for_prefers_last_then_fallback() {
+        let config = RestoreConfig::default();
+        let supported = [PowerMode::Quiet, PowerMode::Performance];
+        assert_eq!(
+            config.mode_for(Some(PowerMode::Quiet), &supported),
+            PowerMode::Quiet
+        );
+        // Balanced (the fallback) isn't supported, so the first of
+        // FALLBACK_MODES that is
+        assert_eq!(config.mode_for(None, &supported), PowerMode::Performance);
+        assert_eq!(
+            config.mode_for(Some(PowerMode::Cool), &[PowerMode::Balanced]),
+            PowerMode::Balanced
+        );
+    }
+
+    #[test]
+    fn target_round_trips_through_args() {
+        let target = target();
+        assert_eq!(RestoreTarget::from_args(&target.to_args()).unwrap(), target);
+
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(RestoreTarget::from_args(&args(&[])).is_err());
+        assert!(RestoreTarget::from_args(&args(&[INTEL_ACPI_PATH])).is_err());
+        assert!(RestoreTarget::from_args(&args(&[INTEL_ACPI_PATH, ""])).is_err());
+        assert!(RestoreTarget::from_args(&args(&[INTEL_ACPI_PATH, "160,7"])).is_err());
+    }
+
+    #[test]
+    fn restores_the_last_mode_from_manual() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetPowerMode, "0x0");
+        respond(&fake, WmaxCommand::GetGMode, "0x0");
+        let mut controller =
+            AcpiController::for_restore(Box::new(fake.clone()), &target(), Some(PowerMode::Quiet));
+
+        let restored = restore_firmware_control(&mut controller, &RestoreConfig::default());
+        assert_eq!(restored.unwrap(), Some(PowerMode::Quiet));
+        assert_eq!(
+            sent(&fake),
+            [
+                WmaxCommand::GetPowerMode,
+                WmaxCommand::GetGMode,
+                WmaxCommand::SetPowerMode(PowerMode::Quiet.code()),
+            ]
+        );
+        assert!(fake.calls().iter().all(|c| c.path == INTEL_ACPI_PATH));
+    }
+
+    #[test]
+    fn leaves_automatic_modes_alone() {
+        let fake = FakeAcpiBackend::new();
+        respond(&fake, WmaxCommand::GetPowerMode, "0xa0");
+        let mut controller = AcpiController::for_restore(Box::new(fake.clone()), &target(), None);
+
+        let restored = restore_firmware_control(&mut controller, &RestoreConfig::default());
+        assert_eq!(restored.unwrap(), None);
+        assert_eq!(sent(&fake), [WmaxCommand::GetPowerMode]);
+    }
+}
@@ -0,0 +1,416 @@
+// Thermal safety watchdog
+// In Manual mode the EC runs the fans at whatever boost was last written,
+// including 0. This thread keeps an eye on the CPU and GPU temperatures
+// and steps in when they get too high: first by raising the fan boost,
+// then by handing control back to a USTT thermal mode.
+
+use crate::acpi::AcpiController;
+use crate::config;
+use crate::power_mode::PowerMode;
+use crate::wmax::FanId;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const WATCHDOG_FILE: &str = "thermal_watchdog.json";
+
+/// Modes tried, in order, when the configured fallback isn't supported
+const FALLBACK_MODES: [PowerMode; 3] = [
+    PowerMode::Performance,
+    PowerMode::Balanced,
+    PowerMode::GMode,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// °C at which the fan boost is raised
+    pub warn_temp: u32,
+    /// Boost the fans get at `warn_temp`
+    pub warn_boost: u8,
+    /// °C at which Manual mode is abandoned
+    pub critical_temp: u32,
+    /// USTT mode to switch to at `critical_temp`
+    pub fallback_mode: PowerMode,
+    pub poll_interval_ms: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            warn_temp: 88,
+            warn_boost: 255,
+            critical_temp: 95,
+            fallback_mode: PowerMode::Performance,
+            poll_interval_ms: 2000,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Config stored in the user's config directory, defaults if missing
+    pub fn load_default() -> Result<Self> {
+        Self::load(&config::config_file(WATCHDOG_FILE)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let config: Self = config::load_json(path)?.unwrap_or_default();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn save_default(&self) -> Result<()> {
+        self.save(&config::config_file(WATCHDOG_FILE)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        config::save_json(path, self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.warn_temp >= self.critical_temp {
+            return Err(anyhow!("warn_temp must be below critical_temp"));
+        }
+        if self.fallback_mode == PowerMode::Manual {
+            return Err(anyhow!("fallback_mode can't be Manual"));
+        }
+        if self.poll_interval_ms < 500 {
+            return Err(anyhow!("poll_interval_ms must be at least 500"));
+        }
+        Ok(())
+    }
+
+    /// `fallback_mode` if the model has it, otherwise the first supported
+    /// mode from `FALLBACK_MODES`
+    fn fallback_for(&self, supported: &[PowerMode]) -> PowerMode {
+        std::iter::once(self.fallback_mode)
+            .chain(FALLBACK_MODES)
+            .find(|m| supported.contains(m))
+            .unwrap_or(self.fallback_mode)
+    }
+}
+
+/// What the watchdog did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Intervention {
+    BoostRaised { temp: u32, boost: u8 },
+    ModeReverted { temp: u32, mode: PowerMode },
+}
+
+impl Intervention {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::BoostRaised { temp, boost } => format!(
+                "{}°C in Manual mode: fans raised to {}%",
+                temp,
+                (*boost as u32 * 100 + 127) / 255
+            ),
+            Self::ModeReverted { temp, mode } => format!(
+                "{}°C in Manual mode: switched back to {}",
+                temp,
+                mode.as_str()
+            ),
+        }
+    }
+}
+
+/// Background thread enforcing a `WatchdogConfig`; stops when dropped
+pub struct ThermalWatchdog {
+    config: Arc<Mutex<WatchdogConfig>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ThermalWatchdog {
+    /// `on_intervention` runs on the watchdog thread after the ACPI lock
+    /// has been released
+    pub fn start<F>(
+        acpi: Arc<Mutex<Option<AcpiController>>>,
+        config: WatchdogConfig,
+        on_intervention: F,
+    ) -> Self
+    where
+        F: Fn(&Intervention) + Send + 'static,
+    {
+        let config = Arc::new(Mutex::new(config));
+        let stop = Arc::new(AtomicBool::new(false));
+        let config_ref = config.clone();
+        let stop_flag = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            info!("Thermal watchdog started");
+            while !stop_flag.load(Ordering::Relaxed) {
+                let config = config_ref.lock().unwrap().clone();
+                if config.enabled {
+                    match Self::check(&acpi, &config) {
+                        Ok(Some(intervention)) => {
+                            warn!("Thermal watchdog: {}", intervention.describe());
+                            on_intervention(&intervention);
+                        }
+                        Ok(None) => {}
+                        Err(e) => debug!("Thermal watchdog check failed: {}", e),
+                    }
+                }
+                std::thread::park_timeout(Duration::from_millis(config.poll_interval_ms));
+            }
+            debug!("Thermal watchdog stopped");
+        });
+
+        Self {
+            config,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn check(
+        acpi: &Mutex<Option<AcpiController>>,
+        config: &WatchdogConfig,
+    ) -> Result<Option<Intervention>> {
+        let mut acpi = acpi.lock().unwrap();
+        let Some(controller) = acpi.as_mut() else {
+            return Ok(None);
+        };
+
+        let (cpu, gpu) = controller.read_temperatures()?;
+        let Some(temp) = cpu.max(gpu) else {
+            return Ok(None);
+        };
+        // Only read the mode once it matters, to keep the poll to one call
+        if temp < config.warn_temp || controller.current_power_mode()? != PowerMode::Manual {
+            return Ok(None);
+        }
+
+        if temp >= config.critical_temp {
+            let mode = config.fallback_for(&controller.power_modes);
+            controller.set_power_mode(mode)?;
+            return Ok(Some(Intervention::ModeReverted { temp, mode }));
+        }
+
+        let mut raised = false;
+        for fan in [FanId::Fan1, FanId::Fan2] {
+            let boost = controller.get_fan_boost(fan).map(|s| s.boost).unwrap_or(0);
+            if boost < config.warn_boost {
+                controller.set_fan_boost(fan, config.warn_boost)?;
+                raised = true;
+            }
+        }
+        Ok(raised.then_some(Intervention::BoostRaised {
+            temp,
+            boost: config.warn_boost,
+        }))
+    }
+
+    pub fn config(&self) -> WatchdogConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Replace the thresholds; applied from the next poll
+    pub fn set_config(&self, config: WatchdogConfig) -> Result<()> {
+        config.validate()?;
+        *self.config.lock().unwrap() = config;
+        Ok(())
+    }
+}
+
+impl Drop for ThermalWatchdog {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acpi::INTEL_ACPI_PATH;
+    use crate::acpi_backend::FakeAcpiBackend;
+    use crate::thermal_restore::RestoreTarget;
+    use crate::wmax::{SensorId, WmaxCommand};
+
+    fn respond(fake: &FakeAcpiBackend, cmd: WmaxCommand, result: &str) {
+        fake.respond(cmd.method(), cmd.args(), result);
+    }
+
+    fn sent(fake: &FakeAcpiBackend) -> Vec<WmaxCommand> {
+        fake.calls()
+            .iter()
+            .filter_map(|c| WmaxCommand::decode(c.method, c.args))
+            .collect()
+    }
+
+    /// Controller on `fake` in `mode` with the CPU at `cpu` °C
+    fn laptop(fake: &FakeAcpiBackend, mode: PowerMode, cpu: u32) -> Mutex<Option<AcpiController>> {
+        respond(
+            fake,
+            WmaxCommand::GetTemp(SensorId::Cpu),
+            &format!("0x{:x}", cpu),
+        );
+        respond(fake, WmaxCommand::GetTemp(SensorId::Gpu), "0x46");
+        respond(
+            fake,
+            WmaxCommand::GetPowerMode,
+            &format!("0x{:x}", mode.code()),
+        );
+        let target = RestoreTarget {
+            acpi_path: INTEL_ACPI_PATH.to_string(),
+            power_modes: vec![
+                PowerMode::Balanced,
+                PowerMode::Performance,
+                PowerMode::Quiet,
+                PowerMode::Manual,
+            ],
+        };
+        Mutex::new(Some(AcpiController::for_restore(
+            Box::new(fake.clone()),
+            &target,
+            None,
+        )))
+    }
+
+    fn is_write(cmd: &WmaxCommand) -> bool {
+        matches!(
+            cmd,
+            WmaxCommand::SetPowerMode(_) | WmaxCommand::SetFanBoost(..) | WmaxCommand::ToggleGMode
+        )
+    }
+
+    #[test]
+    fn warm_manual_mode_raises_both_fans() {
+        let fake = FakeAcpiBackend::new();
+        let acpi = laptop(&fake, PowerMode::Manual, 90);
+        respond(&fake, WmaxCommand::GetFanBoost(FanId::Fan1), "0x40");
+        respond(&fake, WmaxCommand::GetFanBoost(FanId::Fan2), "0x0");
+        let config = WatchdogConfig {
+            warn_boost: 200,
+            ..WatchdogConfig::default()
+        };
+
+        let intervention = ThermalWatchdog::check(&acpi, &config).unwrap();
+        assert_eq!(
+            intervention,
+            Some(Intervention::BoostRaised {
+                temp: 90,
+                boost: 200
+            })
+        );
+        let writes: Vec<WmaxCommand> = sent(&fake).into_iter().filter(is_write).collect();
+        assert_eq!(
+            writes,
+            [
+                WmaxCommand::SetFanBoost(FanId::Fan1, 200),
+                WmaxCommand::SetFanBoost(FanId::Fan2, 200),
+            ]
+        );
+    }
+
+    #[test]
+    fn fans_already_at_warn_boost_are_left_alone() {
+        let fake = FakeAcpiBackend::new();
+        let acpi = laptop(&fake, PowerMode::Manual, 90);
+        respond(&fake, WmaxCommand::GetFanBoost(FanId::Fan1), "0xff");
+        respond(&fake, WmaxCommand::GetFanBoost(FanId::Fan2), "0xff");
+
+        let intervention = ThermalWatchdog::check(&acpi, &WatchdogConfig::default()).unwrap();
+        assert_eq!(intervention, None);
+        assert!(!sent(&fake).iter().any(is_write));
+    }
+
+    #[test]
+    fn critical_temperature_switches_to_the_fallback_mode() {
+        let fake = FakeAcpiBackend::new();
+        let acpi = laptop(&fake, PowerMode::Manual, 96);
+
+        let intervention = ThermalWatchdog::check(&acpi, &WatchdogConfig::default()).unwrap();
+        assert_eq!(
+            intervention,
+            Some(Intervention::ModeReverted {
+                temp: 96,
+                mode: PowerMode::Performance
+            })
+        );
+        let writes: Vec<WmaxCommand> = sent(&fake).into_iter().filter(is_write).collect();
+        assert_eq!(
+            writes,
+            [WmaxCommand::SetPowerMode(PowerMode::Performance.code())]
+        );
+    }
+
+    #[test]
+    fn other_modes_are_only_watched() {
+        let temps = [
+            WmaxCommand::GetTemp(SensorId::Cpu),
+            WmaxCommand::GetTemp(SensorId::Gpu),
+        ];
+
+        // Below warn_temp the temperatures are all that's read
+        let fake = FakeAcpiBackend::new();
+        let acpi = laptop(&fake, PowerMode::Manual, 70);
+        let config = WatchdogConfig::default();
+        assert_eq!(ThermalWatchdog::check(&acpi, &config).unwrap(), None);
+        assert_eq!(sent(&fake), temps);
+
+        // Hot, but the EC is running its own thermal table
+        for mode in [PowerMode::Balanced, PowerMode::Quiet] {
+            let fake = FakeAcpiBackend::new();
+            let acpi = laptop(&fake, mode, 97);
+            assert_eq!(ThermalWatchdog::check(&acpi, &config).unwrap(), None);
+            assert_eq!(sent(&fake), [temps[0], temps[1], WmaxCommand::GetPowerMode]);
+        }
+    }
+
+    #[test]
+    fn fallback_uses_the_first_supported_mode() {
+        let config = WatchdogConfig {
+            fallback_mode: PowerMode::Quiet,
+            ..WatchdogConfig::default()
+        };
+        assert_eq!(
+            config.fallback_for(&[PowerMode::Balanced, PowerMode::Quiet]),
+            PowerMode::Quiet
+        );
+        // Quiet and Performance missing: Balanced is next in FALLBACK_MODES
+        assert_eq!(
+            config.fallback_for(&[PowerMode::GMode, PowerMode::Balanced, PowerMode::Manual]),
+            PowerMode::Balanced
+        );
+        assert_eq!(
+            config.fallback_for(&[PowerMode::GMode, PowerMode::Manual]),
+            PowerMode::GMode
+        );
+        // Nothing usable: keep the configured mode
+        assert_eq!(config.fallback_for(&[PowerMode::Manual]), PowerMode::Quiet);
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(WatchdogConfig::default().validate().is_ok());
+        for config in [
+            WatchdogConfig {
+                warn_temp: 95,
+                ..WatchdogConfig::default()
+            },
+            WatchdogConfig {
+                fallback_mode: PowerMode::Manual,
+                ..WatchdogConfig::default()
+            },
+            WatchdogConfig {
+                poll_interval_ms: 100,
+                ..WatchdogConfig::default()
+            },
+        ] {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+    }
+}
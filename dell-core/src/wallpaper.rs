@@ -0,0 +1,210 @@
+// Keyboard palette derived from an image (e.g. the desktop wallpaper)
+// Median cut over the whole image, then the dominant palette entry of
+// each vertical quarter is assigned to zones 0-3, left to right
+
+use anyhow::{anyhow, Context, Result};
+use image::imageops::FilterType;
+use image::RgbImage;
+use std::path::Path;
+
+// Images are downscaled before quantizing; the result only depends on the
+// pixel data, so the same file always yields the same colors
+const SAMPLE_WIDTH: u32 = 160;
+const PALETTE_SIZE: usize = 8;
+
+pub fn zone_colors_from_image(path: &Path) -> Result<[[u8; 3]; 4]> {
+    let img = image::open(path)
+        .with_context(|| format!("Failed to open image {}", path.display()))?
+        .to_rgb8();
+    zone_colors(&img)
+}
+
+pub fn zone_colors(img: &RgbImage) -> Result<[[u8; 3]; 4]> {
+    if img.width() == 0 || img.height() == 0 {
+        return Err(anyhow!("Image is empty"));
+    }
+
+    let img = if img.width() > SAMPLE_WIDTH {
+        let height = (img.height() as u64 * SAMPLE_WIDTH as u64 / img.width() as u64).max(1);
+        image::imageops::resize(img, SAMPLE_WIDTH, height as u32, FilterType::Triangle)
+    } else {
+        img.clone()
+    };
+
+    let pixels: Vec<[u8; 3]> = img.pixels().map(|p| p.0).collect();
+    let palette = median_cut(&pixels, PALETTE_SIZE);
+
+    let mut zones = [[0u8; 3]; 4];
+    for (zone, color) in zones.iter_mut().enumerate() {
+        let x_start = img.width() * zone as u32 / 4;
+        let x_end = (img.width() * (zone as u32 + 1) / 4).max(x_start + 1);
+
+        let mut counts = vec![0usize; palette.len()];
+        for y in 0..img.height() {
+            for x in x_start..x_end.min(img.width()) {
+                counts[nearest(&palette, img.get_pixel(x, y).0)] += 1;
+            }
+        }
+
+        // Ties go to the lower palette index, which keeps the result stable
+        let best = counts
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(&a.0)))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        *color = palette[best];
+    }
+
+    Ok(zones)
+}
+
+/// Quantize pixels to at most `count` colors with median cut
+pub fn median_cut(pixels: &[[u8; 3]], count: usize) -> Vec<[u8; 3]> {
+    if pixels.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes: Vec<Vec<[u8; 3]>> = vec![pixels.to_vec()];
+
+    while boxes.len() < count {
+        // Split the box with the widest channel range
+        let Some((idx, channel, range)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .max_by(|a, b| a.2.cmp(&b.2).then(b.0.cmp(&a.0)))
+        else {
+            break;
+        };
+
+        if range == 0 {
+            break;
+        }
+
+        let mut bucket = boxes.swap_remove(idx);
+        bucket.sort_by_key(|p| (p[channel], p[(channel + 1) % 3], p[(channel + 2) % 3]));
+        let upper = bucket.split_off(bucket.len() / 2);
+        boxes.push(bucket);
+        boxes.push(upper);
+    }
+
+    // Most populated boxes first
+    boxes.sort_by(|a, b| {
+        b.len()
+            .cmp(&a.len())
+            .then_with(|| average(a).cmp(&average(b)))
+    });
+    boxes.iter().map(|b| average(b)).collect()
+}
+
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    let mut best = (0, 0);
+    for channel in 0..3 {
+        let min = pixels.iter().map(|p| p[channel]).min().unwrap_or(0);
+        let max = pixels.iter().map(|p| p[channel]).max().unwrap_or(0);
+        if max - min > best.1 {
+            best = (channel, max - min);
+        }
+    }
+    best
+}
+
+fn average(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+    }
+    let n = pixels.len().max(1) as u64;
+    [
+        ((sum[0] + n / 2) / n) as u8,
+        ((sum[1] + n / 2) / n) as u8,
+        ((sum[2] + n / 2) / n) as u8,
+    ]
+}
+
+fn nearest(palette: &[[u8; 3]], color: [u8; 3]) -> usize {
+    let distance = |p: &[u8; 3]| -> u32 {
+        (0..3)
+            .map(|c| {
+                let d = p[c] as i32 - color[c] as i32;
+                (d * d) as u32
+            })
+            .sum()
+    };
+
+    palette
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(a.1).cmp(&distance(b.1)).then(a.0.cmp(&b.0)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn stripes_map_to_their_zones() {
+        let colors = zone_colors_from_image(&fixture("stripes.png")).unwrap();
+        assert_eq!(
+            colors,
+            [[200, 30, 30], [30, 200, 30], [30, 30, 200], [220, 220, 40]]
+        );
+    }
+
+    #[test]
+    fn downscaled_image_is_deterministic() {
+        // 320 px wide, so it goes through the resize before quantizing
+        let expected = [
+            [220, 119, 60],
+            [180, 91, 88],
+            [140, 64, 115],
+            [100, 36, 143],
+        ];
+        for _ in 0..2 {
+            assert_eq!(
+                zone_colors_from_image(&fixture("sunset.png")).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn narrow_image_fills_every_zone() {
+        // Fewer columns than zones: each zone still samples one column
+        let img = RgbImage::from_fn(2, 3, |x, _| {
+            Rgb(if x == 0 { [255, 0, 0] } else { [0, 0, 255] })
+        });
+        let colors = zone_colors(&img).unwrap();
+        assert_eq!(colors[0], [255, 0, 0]);
+        assert_eq!(colors[3], [0, 0, 255]);
+    }
+
+    #[test]
+    fn empty_image_is_an_error() {
+        assert!(zone_colors(&RgbImage::new(0, 0)).is_err());
+        assert!(zone_colors_from_image(&fixture("missing.png")).is_err());
+    }
+
+    #[test]
+    fn median_cut_limits_palette_size() {
+        let pixels: Vec<[u8; 3]> = (0..=255).map(|v| [v, 255 - v, 0]).collect();
+        assert_eq!(median_cut(&pixels, 4).len(), 4);
+        assert_eq!(median_cut(&[[9, 9, 9]; 10], 4), vec![[9, 9, 9]]);
+        assert!(median_cut(&[], 4).is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+// Typed WMAX commands understood by the Dell/Alienware ACPI interface
+// Each command knows its method ID and encodes its own argument buffer
+// dell-acpi-helper keeps an allowlist of these; update it with new commands
+
+use anyhow::{anyhow, Result};
+use std::ops::RangeInclusive;
+
+const METHOD_GET: u8 = 0x14;
+const METHOD_SET: u8 = 0x15;
+const METHOD_MODEL: u8 = 0x1a;
+const METHOD_G_MODE: u8 = 0x25;
+
+/// Fan IDs probed during sensor discovery (CPU and GPU fans come first)
+pub const FAN_IDS: RangeInclusive<u8> = 0x32..=0x37;
+/// Temperature sensor IDs probed during sensor discovery
+pub const TEMP_SENSOR_IDS: RangeInclusive<u8> = 0x01..=0x1f;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FanId {
+    /// CPU fan
+    Fan1,
+    /// GPU fan
+    Fan2,
+    /// Further fans found by probing, by WMAX fan ID
+    Extra(u8),
+}
+
+impl FanId {
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Fan1 => 0x32,
+            Self::Fan2 => 0x33,
+            Self::Extra(code) => *code,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x32 => Some(Self::Fan1),
+            0x33 => Some(Self::Fan2),
+            _ if FAN_IDS.contains(&code) => Some(Self::Extra(code)),
+            _ => None,
+        }
+    }
+
+    /// 1-based position, matching hwmon's fanN numbering
+    pub fn index(&self) -> u8 {
+        self.code() - FAN_IDS.start() + 1
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Fan1 => "CPU Fan".to_string(),
+            Self::Fan2 => "GPU Fan".to_string(),
+            Self::Extra(_) => format!("Fan {}", self.index()),
+        }
+    }
+
+    /// Fan from its 1-based index as shown in the UI
+    pub fn from_index(index: u8) -> Result<Self> {
+        match index {
+            1 => Ok(Self::Fan1),
+            2 => Ok(Self::Fan2),
+            _ => index
+                .checked_add(FAN_IDS.start() - 1)
+                .and_then(Self::from_code)
+                .ok_or_else(|| anyhow!("Invalid fan ID: {}", index)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SensorId {
+    Cpu,
+    Gpu,
+    /// Further thermistors found by probing, by WMAX sensor ID
+    Other(u8),
+}
+
+impl SensorId {
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Cpu => 0x01,
+            Self::Gpu => 0x06,
+            Self::Other(code) => *code,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x01 => Some(Self::Cpu),
+            0x06 => Some(Self::Gpu),
+            _ if TEMP_SENSOR_IDS.contains(&code) => Some(Self::Other(code)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Cpu => "CPU".to_string(),
+            Self::Gpu => "GPU".to_string(),
+            Self::Other(code) => format!("Sensor 0x{:02x}", code),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "cpu" => Ok(Self::Cpu),
+            "gpu" => Ok(Self::Gpu),
+            _ => name
+                .strip_prefix("0x")
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .and_then(Self::from_code)
+                .ok_or_else(|| anyhow!("Invalid sensor: {}", name)),
+        }
+    }
+}
+
+/// A fan or temperature sensor reachable through WMAX
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WmaxSensor {
+    Temp(SensorId),
+    Fan(FanId),
+}
+
+impl WmaxSensor {
+    /// The sensors every supported model has
+    pub const BASIC: [Self; 4] = [
+        Self::Temp(SensorId::Cpu),
+        Self::Temp(SensorId::Gpu),
+        Self::Fan(FanId::Fan1),
+        Self::Fan(FanId::Fan2),
+    ];
+
+    pub fn command(&self) -> WmaxCommand {
+        match self {
+            Self::Temp(sensor) => WmaxCommand::GetTemp(*sensor),
+            Self::Fan(fan) => WmaxCommand::GetFanRpm(*fan),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Temp(sensor) => sensor.name(),
+            Self::Fan(fan) => fan.name(),
+        }
+    }
+
+    /// Every ID in the probed ranges
+    pub fn probe_list() -> Vec<Self> {
+        TEMP_SENSOR_IDS
+            .filter_map(SensorId::from_code)
+            .map(Self::Temp)
+            .chain(FAN_IDS.filter_map(FanId::from_code).map(Self::Fan))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WmaxCommand {
+    GetLaptopModel,
+    GetPowerMode,
+    /// Thermal table code, see `power_modes`
+    SetPowerMode(u8),
+    ToggleGMode,
+    GetGMode,
+    GetFanBoost(FanId),
+    SetFanBoost(FanId, u8),
+    GetFanRpm(FanId),
+    GetTemp(SensorId),
+}
+
+impl WmaxCommand {
+    pub fn method(&self) -> u8 {
+        match self {
+            Self::GetLaptopModel => METHOD_MODEL,
+            Self::GetPowerMode | Self::GetFanBoost(_) | Self::GetFanRpm(_) | Self::GetTemp(_) => {
+                METHOD_GET
+            }
+            Self::SetPowerMode(_) | Self::SetFanBoost(..) => METHOD_SET,
+            Self::ToggleGMode | Self::GetGMode => METHOD_G_MODE,
+        }
+    }
+
+    pub fn args(&self) -> [u8; 4] {
+        match self {
+            Self::GetLaptopModel => [0x02, 0x02, 0x00, 0x00],
+            Self::GetPowerMode => [0x0b, 0x00, 0x00, 0x00],
+            Self::SetPowerMode(mode) => [0x01, *mode, 0x00, 0x00],
+            Self::ToggleGMode => [0x01, 0x00, 0x00, 0x00],
+            Self::GetGMode => [0x02, 0x00, 0x00, 0x00],
+            Self::GetFanBoost(fan) => [0x0c, fan.code(), 0x00, 0x00],
+            Self::SetFanBoost(fan, boost) => [0x02, fan.code(), *boost, 0x00],
+            Self::GetFanRpm(fan) => [0x05, fan.code(), 0x00, 0x00],
+            Self::GetTemp(sensor) => [0x04, sensor.code(), 0x00, 0x00],
+        }
+    }
+
+    /// Inverse of `method()`/`args()`, for backends that emulate WMAX
+    pub fn decode(method: u8, args: [u8; 4]) -> Option<Self> {
+        let cmd = match (method, args) {
+            (METHOD_MODEL, [0x02, 0x02, 0x00, 0x00]) => Self::GetLaptopModel,
+            (METHOD_GET, [0x0b, 0x00, 0x00, 0x00]) => Self::GetPowerMode,
+            (METHOD_GET, [0x0c, fan, 0x00, 0x00]) => Self::GetFanBoost(FanId::from_code(fan)?),
+            (METHOD_GET, [0x05, fan, 0x00, 0x00]) => Self::GetFanRpm(FanId::from_code(fan)?),
+            (METHOD_GET, [0x04, sensor, 0x00, 0x00]) => Self::GetTemp(SensorId::from_code(sensor)?),
+            (METHOD_SET, [0x01, mode, 0x00, 0x00]) => Self::SetPowerMode(mode),
+            (METHOD_SET, [0x02, fan, boost, 0x00]) => {
+                Self::SetFanBoost(FanId::from_code(fan)?, boost)
+            }
+            (METHOD_G_MODE, [0x01, 0x00, 0x00, 0x00]) => Self::ToggleGMode,
+            (METHOD_G_MODE, [0x02, 0x00, 0x00, 0x00]) => Self::GetGMode,
+            _ => return None,
+        };
+        Some(cmd)
+    }
+}
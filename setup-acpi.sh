@@ -13,6 +13,28 @@ if [ "$EUID" -ne 0 ]; then
     exit 1
 fi
 
+echo "📋 Installing ACPI helper..."
+echo ""
+
+# Install the privileged helper (build it first with: cargo build --release)
+if [ -f "target/release/dell-acpi-helper" ]; then
+    install -m 755 target/release/dell-acpi-helper /usr/bin/dell-acpi-helper
+    echo "✓ Helper installed in /usr/bin/dell-acpi-helper"
+else
+    echo "❌ target/release/dell-acpi-helper not found, run: cargo build --release -p dell-acpi-helper"
+    exit 1
+fi
+
+# Install polkit action for the helper
+if [ -f "com.jose.dellcontroller.acpi.policy" ]; then
+    install -m 644 com.jose.dellcontroller.acpi.policy /usr/share/polkit-1/actions/
+    echo "✓ Polkit action installed in /usr/share/polkit-1/actions/"
+else
+    echo "❌ File com.jose.dellcontroller.acpi.policy not found"
+    exit 1
+fi
+
+echo ""
 echo "📋 Installing polkit rules..."
 echo ""
 
@@ -61,7 +83,8 @@ echo "✅ Configuration complete!"
 echo "======================================"
 echo ""
 echo "What was done:"
-echo "  - Users in 'wheel' group can use pkexec without password for ACPI"
+echo "  - dell-acpi-helper installed with its own polkit action"
+echo "  - Users in 'wheel' group can run the helper without password"
 echo ""
 echo "⚠️  IMPORTANT:"
 echo "  - LOGOUT and LOGIN again to apply changes"
@@ -1,12 +1,21 @@
 mod hotkey_monitor;
 
-use dell_core::{AcpiController, KeyboardController};
+use dell_core::sysfs_backend::SYSFS_ROOT;
+use dell_core::theme::{default_pywal_path, Theme, ThemeWatcher};
+use dell_core::thermal_restore::{self, RestoreTarget};
+use dell_core::{
+    AcpiController, CalibrationReport, CalibrationSettings, ColorSpace, ColorStop, FanCurveConfig,
+    FanCurveController, FanId, FanState, Gradient, HardwareInfo, HwmonSensors, Intervention,
+    KeyboardController, Palette, PaletteLibrary, PowerMode, Quirk, RestoreConfig, RpmController,
+    RpmTargetConfig, Scene, SensorReading, SensorReadings, ThermalWatchdog, WatchdogConfig,
+};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use tauri::menu::{MenuBuilder, MenuItemBuilder};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tauri::menu::{CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::{Emitter, Manager, State};
 
@@ -15,6 +24,22 @@ pub struct AppState {
     keyboard: Arc<Mutex<Option<KeyboardController>>>,
     acpi: Arc<Mutex<Option<AcpiController>>>,
     turbo_enabled: Arc<AtomicBool>,
+    theme_watcher: Mutex<Option<ThemeWatcher>>,
+    hwmon: HwmonSensors,
+    fan_control: Arc<Mutex<Option<FanAutomation>>>,
+    calibrating: Arc<AtomicBool>,
+    thermal_watchdog: Mutex<Option<ThermalWatchdog>>,
+    restore: Arc<Mutex<RestoreConfig>>,
+    exit_watchdog: Mutex<Option<Child>>,
+}
+
+/// How long exit paths wait for the ACPI lock before giving up
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Background fan control; only one can drive the fans at a time
+enum FanAutomation {
+    Curve(FanCurveController),
+    Rpm(RpmController),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,8 +48,12 @@ pub struct DeviceInfo {
     keyboard_supported: bool,
     power_supported: bool,
     power_modes: Vec<String>,
+    current_power_mode: Option<String>,
+    g_mode_enabled: bool,
     fan_control_limited: bool,
     turbo_enabled: bool,
+    /// DMI and CPU identification, for bug reports
+    hardware: HardwareInfo,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +62,10 @@ pub struct SensorData {
     fan2_rpm: u32,
     cpu_temp: u32,
     gpu_temp: u32,
+    fan1_boost: Option<FanState>,
+    fan2_boost: Option<FanState>,
+    /// Every reading, tagged with its source (WMAX or hwmon driver)
+    sensors: Vec<SensorReading>,
 }
 
 // System setup commands
@@ -111,35 +144,47 @@ fn check_usb_devices(state: State<AppState>) -> Result<Vec<String>, String> {
 #[tauri::command]
 fn init_device(state: State<AppState>) -> Result<DeviceInfo, String> {
     let keyboard = state.keyboard.lock().unwrap();
-    let acpi = state.acpi.lock().unwrap();
+    let mut acpi = state.acpi.lock().unwrap();
 
-    let keyboard_supported = keyboard.is_some();
-    let power_supported = acpi.is_some();
-    let turbo_enabled = state.turbo_enabled.load(Ordering::Relaxed);
-
-    let (model, power_modes, fan_control_limited) = match acpi.as_ref() {
-        Some(acpi_controller) => {
-            let modes: Vec<String> = acpi_controller.power_modes.keys().cloned().collect();
-            let model_str = acpi_controller.model.as_str().to_string();
-            // Fan control is NOT limited on G15 5530 - our tests show it works!
-            // Only limit on specific models that are known to not support manual control
-            let limited = matches!(
-                acpi_controller.model,
-                dell_core::acpi::LaptopModel::G15_5515
-            );
-            (model_str, modes, limited)
-        }
-        None => ("Unknown".to_string(), Vec::new(), false),
+    let mut info = DeviceInfo {
+        model: "Unknown".to_string(),
+        keyboard_supported: keyboard.is_some(),
+        power_supported: acpi.is_some(),
+        power_modes: Vec::new(),
+        current_power_mode: None,
+        g_mode_enabled: false,
+        fan_control_limited: false,
+        turbo_enabled: state.turbo_enabled.load(Ordering::Relaxed),
+        hardware: HardwareInfo::default(),
     };
 
-    Ok(DeviceInfo {
-        model,
-        keyboard_supported,
-        power_supported,
-        power_modes,
-        fan_control_limited,
-        turbo_enabled,
-    })
+    if acpi.is_none() {
+        info.hardware = HardwareInfo::detect();
+    }
+
+    if let Some(acpi_controller) = acpi.as_mut() {
+        info.model = acpi_controller.model.as_str().to_string();
+        info.hardware = acpi_controller.hardware.clone();
+        info.power_modes = acpi_controller
+            .power_modes
+            .iter()
+            .map(|m| m.as_str().to_string())
+            .collect();
+        info.current_power_mode = match acpi_controller.current_power_mode() {
+            Ok(mode) => Some(mode.as_str().to_string()),
+            Err(e) => {
+                log::warn!("Não foi possível ler o modo de energia atual: {}", e);
+                None
+            }
+        };
+        if acpi_controller.power_modes.contains(&PowerMode::GMode) {
+            info.g_mode_enabled = acpi_controller.g_mode_enabled().unwrap_or(false);
+        }
+        // Models whose EC ignores manual fan boost are flagged in the model database
+        info.fan_control_limited = acpi_controller.model.has_quirk(Quirk::FanControlLimited);
+    }
+
+    Ok(info)
 }
 
 #[tauri::command]
@@ -201,6 +246,174 @@ fn set_rainbow(state: State<AppState>, speed: u16) -> Result<String, String> {
     }
 }
 
+#[tauri::command]
+fn set_gradient(
+    state: State<AppState>,
+    stops: Vec<ColorStop>,
+    color_space: String,
+    speed: Option<u16>,
+) -> Result<String, String> {
+    let color_space = ColorSpace::from_name(&color_space).map_err(|e| e.to_string())?;
+    let gradient = Gradient::new(&stops, color_space).map_err(|e| e.to_string())?;
+
+    let keyboard = state.keyboard.lock().unwrap();
+    if let Some(kb) = keyboard.as_ref() {
+        match speed {
+            Some(speed) => kb
+                .set_scrolling_gradient(&gradient, speed)
+                .map_err(|e| e.to_string())?,
+            None => kb.set_gradient(&gradient).map_err(|e| e.to_string())?,
+        }
+        Ok("Gradiente aplicado".to_string())
+    } else {
+        Err("Teclado não encontrado".to_string())
+    }
+}
+
+fn theme_path(path: Option<String>) -> Result<PathBuf, String> {
+    path.map(PathBuf::from)
+        .or_else(default_pywal_path)
+        .ok_or_else(|| "Caminho do tema não encontrado".to_string())
+}
+
+fn apply_theme_colors(keyboard: &Mutex<Option<KeyboardController>>, theme: &Theme) -> Result<(), String> {
+    let colors = theme
+        .zone_colors(theme.format.default_mapping())
+        .map_err(|e| e.to_string())?;
+    let keyboard = keyboard.lock().unwrap();
+    if let Some(kb) = keyboard.as_ref() {
+        kb.set_four_zone_colors(&colors).map_err(|e| e.to_string())
+    } else {
+        Err("Teclado não encontrado".to_string())
+    }
+}
+
+#[tauri::command]
+fn apply_theme(state: State<AppState>, path: Option<String>) -> Result<String, String> {
+    let path = theme_path(path)?;
+    let theme = Theme::load(&path).map_err(|e| e.to_string())?;
+    apply_theme_colors(&state.keyboard, &theme)?;
+    Ok(format!("Tema aplicado: {}", path.display()))
+}
+
+#[tauri::command]
+fn set_theme_sync(
+    state: State<AppState>,
+    enabled: bool,
+    path: Option<String>,
+) -> Result<String, String> {
+    // Dropping the previous watcher joins its thread, which can take a
+    // whole poll interval; don't hold the lock meanwhile
+    let previous = state.theme_watcher.lock().unwrap().take();
+    drop(previous);
+
+    if !enabled {
+        return Ok("Sincronização de tema desativada".to_string());
+    }
+
+    let path = theme_path(path)?;
+    let keyboard = state.keyboard.clone();
+    let watcher = Some(ThemeWatcher::start(
+        path.clone(),
+        std::time::Duration::from_secs(2),
+        move |theme| {
+            if let Err(e) = apply_theme_colors(&keyboard, theme) {
+                log::warn!("Falha ao aplicar tema: {}", e);
+            }
+        },
+    ));
+    // A watcher started concurrently is replaced (and stopped) here
+    let replaced = std::mem::replace(&mut *state.theme_watcher.lock().unwrap(), watcher);
+    drop(replaced);
+
+    Ok(format!("Sincronizando tema de {}", path.display()))
+}
+
+#[tauri::command]
+fn set_wallpaper_colors(state: State<AppState>, path: String) -> Result<String, String> {
+    let colors = dell_core::wallpaper::zone_colors_from_image(Path::new(&path))
+        .map_err(|e| e.to_string())?;
+
+    let keyboard = state.keyboard.lock().unwrap();
+    if let Some(kb) = keyboard.as_ref() {
+        kb.set_four_zone_colors(&colors)
+            .map_err(|e| e.to_string())?;
+        Ok("Cores da imagem aplicadas".to_string())
+    } else {
+        Err("Teclado não encontrado".to_string())
+    }
+}
+
+// Palette library commands
+#[tauri::command]
+fn list_palettes() -> Result<Vec<Palette>, String> {
+    let library = PaletteLibrary::load_default().map_err(|e| e.to_string())?;
+    Ok(library.list())
+}
+
+#[tauri::command]
+fn save_palette(name: String, colors: Vec<[u8; 3]>) -> Result<String, String> {
+    let mut library = PaletteLibrary::load_default().map_err(|e| e.to_string())?;
+    library
+        .save(Palette::new(&name, colors))
+        .map_err(|e| e.to_string())?;
+    Ok(format!("Paleta salva: {}", name))
+}
+
+#[tauri::command]
+fn delete_palette(name: String) -> Result<String, String> {
+    let mut library = PaletteLibrary::load_default().map_err(|e| e.to_string())?;
+    library.delete(&name).map_err(|e| e.to_string())?;
+    Ok(format!("Paleta removida: {}", name))
+}
+
+#[tauri::command]
+fn apply_palette(state: State<AppState>, name: String) -> Result<String, String> {
+    let library = PaletteLibrary::load_default().map_err(|e| e.to_string())?;
+    let palette = library
+        .get(&name)
+        .ok_or_else(|| format!("Paleta não encontrada: {}", name))?;
+    let colors = palette.zone_colors().map_err(|e| e.to_string())?;
+
+    let keyboard = state.keyboard.lock().unwrap();
+    if let Some(kb) = keyboard.as_ref() {
+        kb.set_four_zone_colors(&colors)
+            .map_err(|e| e.to_string())?;
+        Ok(format!("Paleta aplicada: {}", name))
+    } else {
+        Err("Teclado não encontrado".to_string())
+    }
+}
+
+// Scene import/export
+#[tauri::command]
+fn import_scene(state: State<AppState>, path: String) -> Result<String, String> {
+    let scene = Scene::load(Path::new(&path)).map_err(|e| e.to_string())?;
+
+    let keyboard = state.keyboard.lock().unwrap();
+    if let Some(kb) = keyboard.as_ref() {
+        kb.apply_scene(&scene).map_err(|e| e.to_string())?;
+        Ok(format!(
+            "Cena aplicada: {}",
+            scene.name.as_deref().unwrap_or(&path)
+        ))
+    } else {
+        Err("Teclado não encontrado".to_string())
+    }
+}
+
+#[tauri::command]
+fn export_scene(state: State<AppState>, path: String) -> Result<String, String> {
+    let keyboard = state.keyboard.lock().unwrap();
+    if let Some(kb) = keyboard.as_ref() {
+        let scene = kb.export_scene().map_err(|e| e.to_string())?;
+        scene.save(Path::new(&path)).map_err(|e| e.to_string())?;
+        Ok(format!("Cena exportada para {}", path))
+    } else {
+        Err("Teclado não encontrado".to_string())
+    }
+}
+
 // Keyboard LED commands
 #[tauri::command]
 fn set_static_color(
@@ -290,11 +503,14 @@ fn set_dim(state: State<AppState>, level: u8) -> Result<String, String> {
 // Power management commands
 #[tauri::command]
 fn set_power_mode(state: State<AppState>, mode: String) -> Result<String, String> {
+    check_not_calibrating(&state.calibrating)?;
+    stop_fan_automation(&state.fan_control);
     let mut acpi = state.acpi.lock().unwrap();
     if let Some(acpi_controller) = acpi.as_mut() {
         log::info!("Setting power mode to: {}", mode);
-        
-        match acpi_controller.set_power_mode(&mode) {
+        let power_mode = PowerMode::from_name(&mode).map_err(|e| e.to_string())?;
+
+        match acpi_controller.set_power_mode(power_mode) {
             Ok(_) => {
                 // Reset turbo state if changing modes manually
                 state.turbo_enabled.store(false, Ordering::Relaxed);
@@ -314,6 +530,42 @@ fn set_power_mode(state: State<AppState>, mode: String) -> Result<String, String
     }
 }
 
+fn apply_g_mode(state: &AppState, enable: bool) -> Result<String, String> {
+    check_not_calibrating(&state.calibrating)?;
+    stop_fan_automation(&state.fan_control);
+    let mut acpi = state.acpi.lock().unwrap();
+    if let Some(acpi_controller) = acpi.as_mut() {
+        acpi_controller
+            .set_g_mode(enable)
+            .map_err(|e| format!("Erro ao alterar G-Mode: {}", e))?;
+        state.turbo_enabled.store(false, Ordering::Relaxed);
+        Ok(if enable {
+            "🎮 G-Mode ativado".to_string()
+        } else {
+            "G-Mode desativado".to_string()
+        })
+    } else {
+        Err("ACPI not available".to_string())
+    }
+}
+
+#[tauri::command]
+fn set_g_mode(state: State<AppState>, enable: bool) -> Result<String, String> {
+    apply_g_mode(&state, enable)
+}
+
+#[tauri::command]
+fn toggle_g_mode(state: State<AppState>) -> Result<String, String> {
+    let enabled = {
+        let mut acpi = state.acpi.lock().unwrap();
+        let acpi_controller = acpi.as_mut().ok_or("ACPI not available")?;
+        acpi_controller
+            .g_mode_enabled()
+            .map_err(|e| format!("Erro ao ler G-Mode: {}", e))?
+    };
+    apply_g_mode(&state, !enabled)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FanBoostParams {
     pub cpu_rpm: u8,
@@ -322,10 +574,12 @@ pub struct FanBoostParams {
 
 #[tauri::command]
 fn set_fan_boost(state: State<AppState>, params: FanBoostParams) -> Result<String, String> {
+    check_not_calibrating(&state.calibrating)?;
+    stop_fan_automation(&state.fan_control);
     let mut acpi = state.acpi.lock().unwrap();
     if let Some(acpi_controller) = acpi.as_mut() {
         // Ensure we're in Manual mode for fan control
-        if let Err(_) = acpi_controller.set_power_mode("Manual") {
+        if let Err(_) = acpi_controller.set_power_mode(PowerMode::Manual) {
             // Log warning but continue
         }
         state.turbo_enabled.store(false, Ordering::Relaxed);
@@ -341,13 +595,13 @@ fn set_fan_boost(state: State<AppState>, params: FanBoostParams) -> Result<Strin
         let mut errors = Vec::new();
 
         // Set CPU fan (fan 1)
-        match acpi_controller.set_fan_boost(1, cpu_acpi_value) {
+        match acpi_controller.set_fan_boost(FanId::Fan1, cpu_acpi_value) {
             Ok(_) => success_count += 1,
             Err(e) => errors.push(format!("CPU fan: {}", e)),
         }
 
         // Set GPU fan (fan 2)
-        match acpi_controller.set_fan_boost(2, gpu_acpi_value) {
+        match acpi_controller.set_fan_boost(FanId::Fan2, gpu_acpi_value) {
             Ok(_) => success_count += 1,
             Err(e) => errors.push(format!("GPU fan: {}", e)),
         }
@@ -370,12 +624,14 @@ fn set_fan_boost(state: State<AppState>, params: FanBoostParams) -> Result<Strin
 
 #[tauri::command]
 fn set_turbo_mode(state: State<AppState>, enable: bool) -> Result<String, String> {
+    check_not_calibrating(&state.calibrating)?;
+    stop_fan_automation(&state.fan_control);
     let mut acpi = state.acpi.lock().unwrap();
     if let Some(acpi_controller) = acpi.as_mut() {
         if enable {
             // TURBO ON: Max fans
             // Ensure we're in Manual mode for fan control
-            if let Err(_) = acpi_controller.set_power_mode("Manual") {
+            if let Err(_) = acpi_controller.set_power_mode(PowerMode::Manual) {
                 // Log warning but continue
             }
             std::thread::sleep(std::time::Duration::from_millis(200));
@@ -383,11 +639,11 @@ fn set_turbo_mode(state: State<AppState>, enable: bool) -> Result<String, String
             let mut success_count = 0;
             let mut errors = Vec::new();
 
-            match acpi_controller.set_fan_boost(1, 0xFF) {
+            match acpi_controller.set_fan_boost(FanId::Fan1, 0xFF) {
                 Ok(_) => success_count += 1,
                 Err(e) => errors.push(format!("CPU fan: {}", e)),
             }
-            match acpi_controller.set_fan_boost(2, 0xFF) {
+            match acpi_controller.set_fan_boost(FanId::Fan2, 0xFF) {
                 Ok(_) => success_count += 1,
                 Err(e) => errors.push(format!("GPU fan: {}", e)),
             }
@@ -406,7 +662,7 @@ fn set_turbo_mode(state: State<AppState>, enable: bool) -> Result<String, String
             }
         } else {
             // TURBO OFF: Silent mode
-            match acpi_controller.set_power_mode("USTT_Quiet") {
+            match acpi_controller.set_power_mode(PowerMode::Quiet) {
                 Ok(_) => {
                     state.turbo_enabled.store(false, Ordering::Relaxed);
                     Ok("🌙 Modo Silencioso Ativado (Turbo Desligado)".to_string())
@@ -436,26 +692,415 @@ fn toggle_turbo(state: State<AppState>) -> Result<String, String> {
     }
 }
 
+/// Stop the fan curve or RPM control for manual fan or mode changes; a
+/// stopped curve stays off across restarts. Call it before taking the ACPI
+/// lock: the control thread takes that lock too and is joined here.
+fn stop_fan_automation(fan_control: &Mutex<Option<FanAutomation>>) {
+    match fan_control.lock().unwrap().take() {
+        Some(FanAutomation::Curve(controller)) => {
+            let mut config = controller.config();
+            drop(controller);
+
+            config.enabled = false;
+            if let Err(e) = config.save_default() {
+                log::warn!("Falha ao salvar curva de ventiladores: {}", e);
+            }
+            log::info!("Curva de ventiladores desativada pelo controle manual");
+        }
+        Some(FanAutomation::Rpm(_)) => log::info!("Controle de RPM desativado"),
+        None => {}
+    }
+}
+
+/// Errors while a calibration sweep owns the fans and the power mode
+fn check_not_calibrating(calibrating: &AtomicBool) -> Result<(), String> {
+    if calibrating.load(Ordering::SeqCst) {
+        return Err("Aguarde o fim da calibração dos ventiladores".to_string());
+    }
+    Ok(())
+}
+
+/// Errors unless the fans can be driven from software
+fn check_fan_control(state: &AppState) -> Result<(), String> {
+    check_not_calibrating(&state.calibrating)?;
+    let acpi = state.acpi.lock().unwrap();
+    let controller = acpi.as_ref().ok_or("ACPI não disponível")?;
+    if controller.model.has_quirk(Quirk::FanControlLimited) {
+        return Err(
+            "O firmware deste modelo ignora o controle manual dos ventiladores".to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanCurveStatus {
+    config: FanCurveConfig,
+    running: bool,
+}
+
 #[tauri::command]
-fn get_sensors(state: State<AppState>) -> Result<SensorData, String> {
-    let mut acpi = state.acpi.lock().unwrap();
-    if let Some(acpi_controller) = acpi.as_mut() {
-        let fan1_rpm = acpi_controller.get_fan_rpm(1).unwrap_or(0);
-        let fan2_rpm = acpi_controller.get_fan_rpm(2).unwrap_or(0);
-        let cpu_temp = acpi_controller.get_temp("cpu").unwrap_or(0);
-        let gpu_temp = acpi_controller.get_temp("gpu").unwrap_or(0);
-
-        Ok(SensorData {
-            fan1_rpm,
-            fan2_rpm,
-            cpu_temp,
-            gpu_temp,
-        })
+fn get_fan_curve(state: State<AppState>) -> Result<FanCurveStatus, String> {
+    let fan_control = state.fan_control.lock().unwrap();
+    match fan_control.as_ref() {
+        Some(FanAutomation::Curve(controller)) => Ok(FanCurveStatus {
+            config: controller.config(),
+            running: true,
+        }),
+        _ => Ok(FanCurveStatus {
+            config: FanCurveConfig::load_default().map_err(|e| e.to_string())?,
+            running: false,
+        }),
+    }
+}
+
+#[tauri::command]
+fn set_fan_curve(state: State<AppState>, config: FanCurveConfig) -> Result<String, String> {
+    if config.enabled {
+        check_fan_control(&state)?;
+    }
+    config.save_default().map_err(|e| e.to_string())?;
+
+    let mut fan_control = state.fan_control.lock().unwrap();
+    if let Some(FanAutomation::Curve(controller)) = fan_control.as_ref() {
+        if config.enabled {
+            controller.set_config(config).map_err(|e| e.to_string())?;
+            return Ok("✓ Curva de ventiladores atualizada".to_string());
+        }
+        *fan_control = None;
+    }
+    if !config.enabled {
+        return Ok("Curva de ventiladores desativada".to_string());
+    }
+
+    // Stop RPM control before the curve starts writing
+    *fan_control = None;
+    *fan_control = Some(FanAutomation::Curve(FanCurveController::start(
+        state.acpi.clone(),
+        config,
+    )));
+    state.turbo_enabled.store(false, Ordering::Relaxed);
+    Ok("✓ Curva de ventiladores ativa".to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanRpmParams {
+    pub cpu_rpm: Option<u32>,
+    pub gpu_rpm: Option<u32>,
+}
+
+#[tauri::command]
+fn get_fan_rpm_target(state: State<AppState>) -> Result<Option<RpmTargetConfig>, String> {
+    match state.fan_control.lock().unwrap().as_ref() {
+        Some(FanAutomation::Rpm(controller)) => Ok(Some(controller.config())),
+        _ => Ok(None),
+    }
+}
+
+/// Hold the fans at the given speeds; no targets stops RPM control
+#[tauri::command]
+fn set_fan_rpm_target(state: State<AppState>, params: FanRpmParams) -> Result<String, String> {
+    if params.cpu_rpm.is_none() && params.gpu_rpm.is_none() {
+        let mut fan_control = state.fan_control.lock().unwrap();
+        if matches!(fan_control.as_ref(), Some(FanAutomation::Rpm(_))) {
+            *fan_control = None;
+        }
+        return Ok("Controle de RPM desativado".to_string());
+    }
+    check_fan_control(&state)?;
+
+    let describe = |rpm: Option<u32>| rpm.map_or("auto".to_string(), |r| format!("{} RPM", r));
+    let message = format!(
+        "✓ Alvo dos ventiladores: CPU {}, GPU {}",
+        describe(params.cpu_rpm),
+        describe(params.gpu_rpm)
+    );
+
+    let mut fan_control = state.fan_control.lock().unwrap();
+    if let Some(FanAutomation::Rpm(controller)) = fan_control.as_ref() {
+        let config = RpmTargetConfig {
+            cpu_rpm: params.cpu_rpm,
+            gpu_rpm: params.gpu_rpm,
+            ..controller.config()
+        };
+        controller.set_config(config).map_err(|e| e.to_string())?;
+        return Ok(message);
+    }
+
+    // A running fan curve is replaced, and stays off like any manual change
+    drop(fan_control);
+    stop_fan_automation(&state.fan_control);
+    let config = RpmTargetConfig::new(params.cpu_rpm, params.gpu_rpm);
+    *state.fan_control.lock().unwrap() = Some(FanAutomation::Rpm(RpmController::start(
+        state.acpi.clone(),
+        config,
+    )));
+    state.turbo_enabled.store(false, Ordering::Relaxed);
+    Ok(message)
+}
+
+/// Last calibration saved for this machine
+#[tauri::command]
+fn get_fan_calibration(state: State<AppState>) -> Result<Option<CalibrationReport>, String> {
+    let machine = {
+        let acpi = state.acpi.lock().unwrap();
+        let controller = acpi.as_ref().ok_or("ACPI não disponível")?;
+        CalibrationReport::machine_key(&controller.hardware, &controller.model)
+    };
+    CalibrationReport::load_default(&machine).map_err(|e| e.to_string())
+}
+
+/// Start the fan sweep in the background. Progress comes as
+/// `fan-calibration-progress` events and the report as
+/// `fan-calibration-finished` (or `fan-calibration-failed`).
+#[tauri::command]
+fn start_fan_calibration(app: tauri::AppHandle, state: State<AppState>) -> Result<String, String> {
+    check_fan_control(&state)?;
+    if state.calibrating.swap(true, Ordering::SeqCst) {
+        return Err("Calibração já em andamento".to_string());
+    }
+    stop_fan_automation(&state.fan_control);
+    state.turbo_enabled.store(false, Ordering::Relaxed);
+
+    let acpi = state.acpi.clone();
+    let calibrating = state.calibrating.clone();
+    std::thread::spawn(move || {
+        let result = dell_core::fan_calibration::calibrate(
+            &acpi,
+            &CalibrationSettings::default(),
+            |progress| {
+                let _ = app.emit("fan-calibration-progress", progress);
+            },
+        );
+        match result {
+            Ok(report) => {
+                log::info!("Relatório de calibração:\n{}", report.to_text());
+                if let Err(e) = report.save_default() {
+                    log::warn!("Falha ao salvar calibração: {}", e);
+                }
+                let _ = app.emit("fan-calibration-finished", report);
+            }
+            Err(e) => {
+                log::error!("Falha na calibração dos ventiladores: {}", e);
+                let _ = app.emit("fan-calibration-failed", e.to_string());
+            }
+        }
+        calibrating.store(false, Ordering::SeqCst);
+    });
+    Ok("Calibração iniciada".to_string())
+}
+
+#[tauri::command]
+fn get_thermal_watchdog(state: State<AppState>) -> Result<WatchdogConfig, String> {
+    match state.thermal_watchdog.lock().unwrap().as_ref() {
+        Some(watchdog) => Ok(watchdog.config()),
+        None => WatchdogConfig::load_default().map_err(|e| e.to_string()),
+    }
+}
+
+#[tauri::command]
+fn set_thermal_watchdog(state: State<AppState>, config: WatchdogConfig) -> Result<String, String> {
+    config.save_default().map_err(|e| e.to_string())?;
+    if let Some(watchdog) = state.thermal_watchdog.lock().unwrap().as_ref() {
+        watchdog.set_config(config.clone()).map_err(|e| e.to_string())?;
+    }
+    Ok(if config.enabled {
+        format!(
+            "✓ Proteção térmica: {}°C / {}°C",
+            config.warn_temp, config.critical_temp
+        )
     } else {
-        Err("ACPI not available".to_string())
+        "⚠️ Proteção térmica desativada".to_string()
+    })
+}
+
+/// Watch temperatures while in Manual mode. An intervention also stops the
+/// fan curve or RPM control, which would otherwise undo it.
+fn start_thermal_watchdog(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    if state.acpi.lock().unwrap().is_none() {
+        return;
+    }
+    let config = WatchdogConfig::load_default().unwrap_or_else(|e| {
+        log::warn!("Configuração da proteção térmica inválida, usando padrões: {}", e);
+        WatchdogConfig::default()
+    });
+
+    let app_handle = app.clone();
+    let fan_control = state.fan_control.clone();
+    let turbo_enabled = state.turbo_enabled.clone();
+    let watchdog = ThermalWatchdog::start(state.acpi.clone(), config, move |intervention| {
+        stop_fan_automation(&fan_control);
+        turbo_enabled.store(false, Ordering::Relaxed);
+        let message = intervention_message(&intervention);
+        send_notification("Dell G-Series - Proteção Térmica", &message);
+        let _ = app_handle.emit("thermal-intervention", intervention);
+    });
+    *state.thermal_watchdog.lock().unwrap() = Some(watchdog);
+}
+
+fn intervention_message(intervention: &Intervention) -> String {
+    match intervention {
+        Intervention::BoostRaised { temp, boost } => format!(
+            "Temperatura em {}°C no modo Manual: ventiladores elevados para {}%",
+            temp,
+            FanState::from_boost(*boost).percent
+        ),
+        Intervention::ModeReverted { temp, mode } => format!(
+            "Temperatura em {}°C no modo Manual: modo {} restaurado",
+            temp,
+            mode.display_name()
+        ),
     }
 }
 
+#[tauri::command]
+fn get_restore_config(state: State<AppState>) -> Result<RestoreConfig, String> {
+    Ok(state.restore.lock().unwrap().clone())
+}
+
+#[tauri::command]
+fn set_restore_config(state: State<AppState>, config: RestoreConfig) -> Result<String, String> {
+    config.save_default().map_err(|e| e.to_string())?;
+    let target = state.acpi.lock().unwrap().as_ref().map(RestoreTarget::of);
+    if let Some(target) = target {
+        update_exit_watchdog(&state.exit_watchdog, &config, &target);
+    }
+    let message = match (config.enabled, config.watchdog_process) {
+        (true, true) => "✓ Controle térmico restaurado ao sair, com processo de vigia",
+        (true, false) => "✓ Controle térmico restaurado ao sair",
+        (false, _) => "⚠️ Restauração do controle térmico ao sair desativada",
+    };
+    *state.restore.lock().unwrap() = config;
+    Ok(message.to_string())
+}
+
+/// Start or stop the process that restores thermal control if the app dies
+/// without running its exit handlers
+fn update_exit_watchdog(
+    exit_watchdog: &Mutex<Option<Child>>,
+    config: &RestoreConfig,
+    target: &RestoreTarget,
+) {
+    let mut child = exit_watchdog.lock().unwrap();
+    if config.enabled && config.watchdog_process {
+        if child.is_none() {
+            match thermal_restore::spawn_exit_watchdog(target) {
+                Ok(process) => *child = Some(process),
+                Err(e) => log::warn!("Falha ao iniciar processo de vigia: {}", e),
+            }
+        }
+    } else if let Some(mut process) = child.take() {
+        // Killed, it restores nothing
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+}
+
+/// Hand the fans back to the firmware before the process goes away. The
+/// background controllers are stopped first so they can't switch back to
+/// Manual; a saved fan curve stays enabled for the next start.
+fn restore_on_exit(state: &AppState) {
+    drop(state.fan_control.lock().unwrap_or_else(PoisonError::into_inner).take());
+    drop(state.thermal_watchdog.lock().unwrap_or_else(PoisonError::into_inner).take());
+
+    let config = state.restore.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    let restored = thermal_restore::restore_locked(&state.acpi, &config, RESTORE_TIMEOUT);
+    match &restored {
+        Ok(Some(mode)) => log::info!("Controle térmico devolvido ao firmware: {}", mode.as_str()),
+        Ok(None) => {}
+        Err(e) => log::error!("Falha ao restaurar controle térmico: {}", e),
+    }
+
+    // The watchdog process stays as a second try if this one failed
+    if restored.is_ok() {
+        let mut child = state.exit_watchdog.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mut process) = child.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// Restore thermal control when any thread panics, after the default
+/// panic output. If that left Manual mode the process aborts: the fan
+/// curve or RPM control would otherwise keep running and switch straight
+/// back. They can't be stopped from here, since the panicking thread may
+/// be theirs.
+fn install_restore_panic_hook(
+    acpi: Arc<Mutex<Option<AcpiController>>>,
+    restore: Arc<Mutex<RestoreConfig>>,
+) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        let config = restore.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        match thermal_restore::restore_locked(&acpi, &config, RESTORE_TIMEOUT) {
+            Ok(Some(mode)) => {
+                log::warn!("Pânico: controle térmico restaurado ({}), encerrando", mode.as_str());
+                std::process::abort();
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Pânico: falha ao restaurar controle térmico: {}", e),
+        }
+    }));
+}
+
+/// SIGTERM, SIGINT and SIGHUP restore thermal control, then exit
+fn start_signal_handler(app: &tauri::AppHandle) {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+    let mut signals = match signal_hook::iterator::Signals::new([SIGTERM, SIGINT, SIGHUP]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            log::warn!("Falha ao registrar sinais: {}", e);
+            return;
+        }
+    };
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            log::info!("Sinal {} recebido, encerrando", signal);
+            restore_on_exit(&app_handle.state::<AppState>());
+            std::process::exit(128 + signal);
+        }
+    });
+}
+
+#[tauri::command]
+fn get_sensors(state: State<AppState>) -> Result<SensorData, String> {
+    let mut readings = {
+        let mut acpi = state.acpi.lock().unwrap();
+        match acpi.as_mut().map(|c| c.read_all_sensors()) {
+            Some(Ok(readings)) => readings,
+            Some(Err(e)) => {
+                log::warn!("Falha ao ler sensores via ACPI: {}", e);
+                SensorReadings::default()
+            }
+            None => SensorReadings::default(),
+        }
+    };
+
+    let hwmon = state.hwmon.read_all();
+    if hwmon.is_empty() && readings == SensorReadings::default() {
+        return Err("Nenhum sensor disponível".to_string());
+    }
+
+    let mut sensors = readings.to_readings();
+    readings.fill_missing(&hwmon);
+    sensors.extend(hwmon);
+
+    Ok(SensorData {
+        fan1_rpm: readings.fan1_rpm.unwrap_or(0),
+        fan2_rpm: readings.fan2_rpm.unwrap_or(0),
+        cpu_temp: readings.cpu_temp.unwrap_or(0),
+        gpu_temp: readings.gpu_temp.unwrap_or(0),
+        fan1_boost: readings.fan1_boost,
+        fan2_boost: readings.fan2_boost,
+        sensors,
+    })
+}
+
 fn send_notification(title: &str, body: &str) {
     match Command::new("notify-send")
         .arg("--app-name=Dell Controller")
@@ -472,6 +1117,18 @@ fn send_notification(title: &str, body: &str) {
     }
 }
 
+/// Entry point of the exit watchdog process, see `RestoreConfig`
+pub fn run_exit_watchdog() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .format_timestamp_millis()
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(2).collect();
+    if let Err(e) = thermal_restore::run_exit_watchdog(&args) {
+        log::error!("Processo de vigia: falha ao restaurar controle térmico: {}", e);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize logger with timestamp and module info
@@ -482,15 +1139,21 @@ pub fn run() {
     log::info!("Starting Dell G-Series Controller...");
 
     // Initialize controllers
-    let keyboard = Arc::new(Mutex::new(KeyboardController::new(false).ok()));
     let turbo_enabled = Arc::new(AtomicBool::new(false));
 
     // Try to create ACPI controller and log any errors
     log::info!("Initializing ACPI controller...");
     let acpi_result = AcpiController::new();
     let acpi = Arc::new(Mutex::new(match acpi_result {
-        Ok(controller) => {
+        Ok(mut controller) => {
             log::info!("✓ ACPI controller initialized successfully. Model: {}", controller.model.as_str());
+            if let Err(e) = controller.discover_sensors() {
+                log::warn!("Falha ao descobrir sensores WMAX: {}", e);
+            }
+            match thermal_restore::last_mode_path() {
+                Ok(path) => controller.track_last_mode(path),
+                Err(e) => log::warn!("Modo de energia anterior não será salvo: {}", e),
+            }
             Some(controller)
         }
         Err(e) => {
@@ -499,6 +1162,50 @@ pub fn run() {
         }
     }));
 
+    let restore = RestoreConfig::load_default().unwrap_or_else(|e| {
+        log::warn!("Configuração de restauração inválida, usando padrões: {}", e);
+        RestoreConfig::default()
+    });
+    let exit_watchdog = Mutex::new(None);
+    let target = acpi.lock().unwrap().as_ref().map(RestoreTarget::of);
+    if let Some(target) = target {
+        update_exit_watchdog(&exit_watchdog, &restore, &target);
+    }
+    let restore = Arc::new(Mutex::new(restore));
+    install_restore_panic_hook(acpi.clone(), restore.clone());
+
+    let fan_curve = match FanCurveConfig::load_default() {
+        Ok(config) if config.enabled && acpi.lock().unwrap().is_some() => {
+            log::info!("Iniciando curva de ventiladores salva");
+            Some(FanAutomation::Curve(FanCurveController::start(
+                acpi.clone(),
+                config,
+            )))
+        }
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("Falha ao carregar curva de ventiladores: {}", e);
+            None
+        }
+    };
+    let fan_control = Arc::new(Mutex::new(fan_curve));
+
+    // Known models say which ELC product IDs and how many zones to use
+    // (Alienware has extra logo/lightbar zones)
+    let keyboard_model = acpi
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|c| c.model.clone())
+        .filter(|m| !m.is_unknown() && m.supports_keyboard());
+    let keyboard = match keyboard_model {
+        Some(model) => {
+            KeyboardController::open(&model.keyboard_product_ids, model.keyboard_zones, false)
+        }
+        None => KeyboardController::new(false),
+    };
+    let keyboard = Arc::new(Mutex::new(keyboard.ok()));
+
     // Initialize hotkey monitor for F9 (works on Wayland and X11)
     log::info!("Initializing F9 hotkey monitor via evdev...");
     let hotkey_monitor = match hotkey_monitor::HotkeyMonitor::new(evdev::Key::KEY_F9) {
@@ -512,8 +1219,11 @@ pub fn run() {
         }
     };
 
+    let calibrating = Arc::new(AtomicBool::new(false));
     let turbo_for_hotkey = turbo_enabled.clone();
     let acpi_for_hotkey = acpi.clone();
+    let fan_control_for_hotkey = fan_control.clone();
+    let calibrating_for_hotkey = calibrating.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -534,6 +1244,13 @@ pub fn run() {
             keyboard,
             acpi,
             turbo_enabled,
+            theme_watcher: Mutex::new(None),
+            hwmon: HwmonSensors::discover(Path::new(SYSFS_ROOT)),
+            fan_control,
+            calibrating,
+            thermal_watchdog: Mutex::new(None),
+            restore,
+            exit_watchdog,
         })
         .invoke_handler(tauri::generate_handler![
             check_permissions,
@@ -549,11 +1266,33 @@ pub fn run() {
             set_fan_boost,
             set_turbo_mode,
             toggle_turbo,
+            set_g_mode,
+            toggle_g_mode,
             get_sensors,
+            get_fan_curve,
+            set_fan_curve,
+            get_fan_rpm_target,
+            set_fan_rpm_target,
+            get_fan_calibration,
+            start_fan_calibration,
+            get_thermal_watchdog,
+            set_thermal_watchdog,
+            get_restore_config,
+            set_restore_config,
             set_pulse_effect,
             set_zone_colors,
             set_spectrum,
             set_rainbow,
+            set_gradient,
+            apply_theme,
+            set_theme_sync,
+            set_wallpaper_colors,
+            list_palettes,
+            save_palette,
+            delete_palette,
+            apply_palette,
+            import_scene,
+            export_scene,
         ])
         .setup(|app| {
             // Check for --minimized flag
@@ -570,6 +1309,9 @@ pub fn run() {
                 }
             }
 
+            start_thermal_watchdog(app.handle());
+            start_signal_handler(app.handle());
+
             // Start F9 hotkey polling (evdev - works on Wayland and X11)
             if let Some(monitor) = hotkey_monitor {
                 let app_handle = app.handle().clone();
@@ -578,15 +1320,21 @@ pub fn run() {
                         std::thread::sleep(std::time::Duration::from_millis(50));
                         if monitor.try_recv() {
                             log::info!("F9 detectado via evdev - toggling turbo");
+                            if let Err(e) = check_not_calibrating(&calibrating_for_hotkey) {
+                                log::warn!("F9 ignorado: {}", e);
+                                send_notification("Erro no Modo Turbo", &e);
+                                continue;
+                            }
 
                             let is_turbo = turbo_for_hotkey.load(Ordering::Relaxed);
+                            stop_fan_automation(&fan_control_for_hotkey);
                             let mut acpi_guard = acpi_for_hotkey.lock().unwrap();
                             if let Some(acpi_ctrl) = acpi_guard.as_mut() {
                                 let result = if !is_turbo {
-                                    let _ = acpi_ctrl.set_power_mode("Manual");
+                                    let _ = acpi_ctrl.set_power_mode(PowerMode::Manual);
                                     std::thread::sleep(std::time::Duration::from_millis(200));
-                                    let r1 = acpi_ctrl.set_fan_boost(1, 0xFF);
-                                    let r2 = acpi_ctrl.set_fan_boost(2, 0xFF);
+                                    let r1 = acpi_ctrl.set_fan_boost(FanId::Fan1, 0xFF);
+                                    let r2 = acpi_ctrl.set_fan_boost(FanId::Fan2, 0xFF);
                                     if r1.is_ok() || r2.is_ok() {
                                         turbo_for_hotkey.store(true, Ordering::Relaxed);
                                         Ok("MODO TURBO ATIVADO - Ventiladores em 100%".to_string())
@@ -594,7 +1342,7 @@ pub fn run() {
                                         Err("Falha ao ativar turbo".to_string())
                                     }
                                 } else {
-                                    match acpi_ctrl.set_power_mode("USTT_Quiet") {
+                                    match acpi_ctrl.set_power_mode(PowerMode::Quiet) {
                                         Ok(_) => {
                                             turbo_for_hotkey.store(false, Ordering::Relaxed);
                                             Ok("Modo Silencioso (Turbo Desligado)".to_string())
@@ -623,18 +1371,49 @@ pub fn run() {
 
             // Create system tray
             let show = MenuItemBuilder::with_id("show", "Mostrar").build(app)?;
+            let g_mode_on = {
+                let state = app.state::<AppState>();
+                let mut acpi = state.acpi.lock().unwrap();
+                acpi.as_mut()
+                    .map(|a| a.g_mode_enabled().unwrap_or(false))
+                    .unwrap_or(false)
+            };
+            let g_mode = CheckMenuItemBuilder::with_id("g_mode", "G-Mode")
+                .checked(g_mode_on)
+                .build(app)?;
             let quit = MenuItemBuilder::with_id("quit", "Sair").build(app)?;
-            let menu = MenuBuilder::new(app).item(&show).item(&quit).build()?;
+            let menu = MenuBuilder::new(app)
+                .item(&show)
+                .item(&g_mode)
+                .separator()
+                .item(&quit)
+                .build()?;
 
             let _tray = TrayIconBuilder::new()
                 .menu(&menu)
-                .on_menu_event(|app, event| match event.id().as_ref() {
+                .on_menu_event(move |app, event| match event.id().as_ref() {
                     "show" => {
                         if let Some(window) = app.get_webview_window("main") {
                             let _ = window.show();
                             let _ = window.set_focus();
                         }
                     }
+                    "g_mode" => {
+                        // The check item flips itself on click
+                        let enable = g_mode.is_checked().unwrap_or(false);
+                        let state = app.state::<AppState>();
+                        match apply_g_mode(&state, enable) {
+                            Ok(msg) => {
+                                send_notification("Dell G-Series - G-Mode", &msg);
+                                let _ = app.emit("g-mode-toggled", enable);
+                            }
+                            Err(e) => {
+                                log::error!("Falha ao alterar G-Mode: {}", e);
+                                let _ = g_mode.set_checked(!enable);
+                                send_notification("Erro no G-Mode", &e);
+                            }
+                        }
+                    }
                     "quit" => {
                         app.exit(0);
                     }
@@ -665,6 +1444,12 @@ pub fn run() {
                 api.prevent_close();
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Tray "Sair" and any other clean exit
+            if let tauri::RunEvent::Exit = event {
+                restore_on_exit(&app.state::<AppState>());
+            }
+        });
 }
@@ -2,6 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // Spawned by the app itself to restore thermal control after it exits
+    if std::env::args().nth(1).as_deref() == Some(dell_core::thermal_restore::WATCHDOG_ARG) {
+        return dell_gui_tauri_lib::run_exit_watchdog();
+    }
+
     // Set environment variables to fix GBM buffer issues on Linux
     std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
     std::env::set_var("GDK_BACKEND", "x11");
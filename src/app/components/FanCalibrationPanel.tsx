@@ -0,0 +1,116 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Anomaly, CalibrationProgress, CalibrationReport } from "../types";
+
+interface FanCalibrationPanelProps {
+  showStatus: (message: string, isError?: boolean) => void;
+  fanControlLimited?: boolean;
+}
+
+function describeAnomaly(anomaly: Anomaly): string {
+  switch (anomaly.kind) {
+    case "no_reading":
+      return `${anomaly.fan}: nenhuma leitura de RPM`;
+    case "not_spinning":
+      return `${anomaly.fan}: parado mesmo com boost ${anomaly.boost}`;
+    case "not_responding":
+      return `${anomaly.fan}: não responde ao boost (${anomaly.low_rpm} → ${anomaly.high_rpm} RPM)`;
+    case "asymmetry":
+      return `Ventiladores desbalanceados: ${anomaly.fan1_rpm} vs ${anomaly.fan2_rpm} RPM`;
+  }
+}
+
+export function FanCalibrationPanel({
+  showStatus,
+  fanControlLimited = false,
+}: FanCalibrationPanelProps) {
+  const [report, setReport] = useState<CalibrationReport | null>(null);
+  const [progress, setProgress] = useState<CalibrationProgress | null>(null);
+
+  useEffect(() => {
+    invoke<CalibrationReport | null>("get_fan_calibration")
+      .then(setReport)
+      .catch(() => setReport(null));
+
+    const unlisteners = [
+      listen<CalibrationProgress>("fan-calibration-progress", (event) =>
+        setProgress(event.payload)
+      ),
+      listen<CalibrationReport>("fan-calibration-finished", (event) => {
+        setProgress(null);
+        setReport(event.payload);
+        showStatus(
+          event.payload.anomalies.length === 0
+            ? "✓ Calibração concluída sem problemas"
+            : `Calibração concluída: ${event.payload.anomalies.length} problema(s)`,
+          event.payload.anomalies.length > 0
+        );
+      }),
+      listen<string>("fan-calibration-failed", (event) => {
+        setProgress(null);
+        showStatus("Erro na calibração: " + event.payload, true);
+      }),
+    ];
+    return () => {
+      unlisteners.forEach((u) => u.then((f) => f()));
+    };
+  }, []);
+
+  const start = async () => {
+    try {
+      const result: string = await invoke("start_fan_calibration");
+      setProgress({ step: 0, steps: 0, boost: 0 });
+      showStatus(result);
+    } catch (error) {
+      showStatus("Erro: " + String(error), true);
+    }
+  };
+
+  return (
+    <div className="section">
+      <h3>Calibração dos Ventiladores</h3>
+
+      {report && (
+        <>
+          <div className="sensor-display">
+            {report.fans.map((fan) => (
+              <div className="sensor-item" key={fan.fan}>
+                <span className="sensor-label">{fan.fan}</span>
+                {fan.points.map((p) => (
+                  <span className="sensor-label" key={p.boost}>
+                    {Math.round((p.boost * 100) / 255)}% →{" "}
+                    {p.rpm ?? "-"} RPM{p.settled ? "" : "*"}
+                  </span>
+                ))}
+              </div>
+            ))}
+          </div>
+          {report.anomalies.length > 0 && (
+            <div className="warning-box">
+              {report.anomalies.map((a, i) => (
+                <p key={i}>⚠️ {describeAnomaly(a)}</p>
+              ))}
+            </div>
+          )}
+          <p className="info-text">
+            Última calibração:{" "}
+            {new Date(report.created_at * 1000).toLocaleString()}
+          </p>
+        </>
+      )}
+
+      <button
+        className="apply-btn"
+        onClick={start}
+        disabled={fanControlLimited || progress !== null}
+      >
+        {progress
+          ? progress.steps > 0
+            ? `Calibrando... ${progress.step}/${progress.steps}`
+            : "Calibrando..."
+          : "Calibrar e Testar"}
+      </button>
+    </div>
+  );
+}
@@ -7,6 +7,11 @@ interface FanControlProps {
   onFan1Change: (value: number) => void;
   onFan2Change: (value: number) => void;
   onApply: () => void;
+  rpm1: number;
+  rpm2: number;
+  onRpm1Change: (value: number) => void;
+  onRpm2Change: (value: number) => void;
+  onApplyRpm: () => void;
   fanControlLimited?: boolean;
 }
 
@@ -16,6 +21,11 @@ export function FanControl({
   onFan1Change,
   onFan2Change,
   onApply,
+  rpm1,
+  rpm2,
+  onRpm1Change,
+  onRpm2Change,
+  onApplyRpm,
   fanControlLimited = false,
 }: FanControlProps) {
   const { updateSetting } = usePersistedSettings();
@@ -89,6 +99,39 @@ export function FanControl({
       >
         {fanControlLimited ? "Controle Indisponível" : "Aplicar Velocidades"}
       </button>
+
+      <h3>Velocidade Alvo</h3>
+      <div className="slider-group">
+        <label>🌀 Ventilador CPU: {rpm1} RPM</label>
+        <input
+          type="range"
+          min="0"
+          max="6000"
+          step="100"
+          value={rpm1}
+          onChange={(e) => onRpm1Change(Number(e.target.value))}
+          disabled={fanControlLimited}
+        />
+      </div>
+      <div className="slider-group">
+        <label>💨 Ventilador GPU: {rpm2} RPM</label>
+        <input
+          type="range"
+          min="0"
+          max="6000"
+          step="100"
+          value={rpm2}
+          onChange={(e) => onRpm2Change(Number(e.target.value))}
+          disabled={fanControlLimited}
+        />
+      </div>
+      <button
+        className="apply-btn"
+        onClick={onApplyRpm}
+        disabled={fanControlLimited}
+      >
+        Manter RPM
+      </button>
     </div>
   );
 }
@@ -0,0 +1,200 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import {
+  CurvePoint,
+  FanCurve,
+  FanCurveConfig,
+  FanCurveStatus,
+  TempSource,
+} from "../types";
+
+interface FanCurveControlProps {
+  showStatus: (message: string, isError?: boolean) => void;
+  fanControlLimited?: boolean;
+}
+
+const toPercent = (boost: number) => Math.round((boost * 100) / 255);
+const toBoost = (percent: number) => Math.round((percent * 255) / 100);
+
+interface CurveEditorProps {
+  title: string;
+  curve: FanCurve;
+  sources: TempSource[];
+  onChange: (curve: FanCurve) => void;
+}
+
+const SOURCE_LABELS: Record<TempSource, string> = {
+  cpu: "Temperatura da CPU",
+  gpu: "Temperatura da GPU",
+  max: "Maior entre CPU e GPU",
+};
+
+function CurveEditor({ title, curve, sources, onChange }: CurveEditorProps) {
+  const updatePoint = (index: number, point: CurvePoint) => {
+    const points = curve.points.map((p, i) => (i === index ? point : p));
+    onChange({ ...curve, points });
+  };
+
+  return (
+    <div className="slider-group">
+      <label>{title}</label>
+      {sources.length > 1 && (
+        <select
+          className="dark-select"
+          value={curve.source}
+          onChange={(e) =>
+            onChange({ ...curve, source: e.target.value as TempSource })
+          }
+        >
+          {sources.map((s) => (
+            <option key={s} value={s}>
+              {SOURCE_LABELS[s]}
+            </option>
+          ))}
+        </select>
+      )}
+      {curve.points.map((point, i) => (
+        <div className="slider-group" key={i}>
+          <label>
+            {point.temp}°C → {toPercent(point.boost)}%
+          </label>
+          <input
+            type="range"
+            min="30"
+            max="100"
+            value={point.temp}
+            onChange={(e) =>
+              updatePoint(i, { ...point, temp: Number(e.target.value) })
+            }
+          />
+          <input
+            type="range"
+            min="0"
+            max="100"
+            value={toPercent(point.boost)}
+            onChange={(e) =>
+              updatePoint(i, { ...point, boost: toBoost(Number(e.target.value)) })
+            }
+          />
+        </div>
+      ))}
+    </div>
+  );
+}
+
+export function FanCurveControl({
+  showStatus,
+  fanControlLimited = false,
+}: FanCurveControlProps) {
+  const [config, setConfig] = useState<FanCurveConfig | null>(null);
+  const [running, setRunning] = useState(false);
+
+  const load = async () => {
+    try {
+      const status: FanCurveStatus = await invoke("get_fan_curve");
+      setConfig(status.config);
+      setRunning(status.running);
+    } catch (error) {
+      showStatus("Erro ao carregar curva: " + String(error), true);
+    }
+  };
+
+  useEffect(() => {
+    load();
+  }, []);
+
+  const apply = async (next: FanCurveConfig) => {
+    try {
+      const result: string = await invoke("set_fan_curve", { config: next });
+      showStatus(result);
+    } catch (error) {
+      showStatus("Erro: " + String(error), true);
+    }
+    // Manual fan or mode changes also stop the curve, so re-read the state
+    await load();
+  };
+
+  if (!config) return null;
+
+  return (
+    <div className="section">
+      <h3>Curva de Ventiladores</h3>
+
+      <CurveEditor
+        title="🌀 Ventilador CPU"
+        curve={config.cpu_fan}
+        sources={["cpu"]}
+        onChange={(cpu_fan) => setConfig({ ...config, cpu_fan })}
+      />
+      <CurveEditor
+        title="💨 Ventilador GPU"
+        curve={config.gpu_fan}
+        sources={["gpu", "max"]}
+        onChange={(gpu_fan) => setConfig({ ...config, gpu_fan })}
+      />
+
+      <div className="slider-group">
+        <label>Histerese: {config.hysteresis}°C</label>
+        <input
+          type="range"
+          min="0"
+          max="15"
+          value={config.hysteresis}
+          onChange={(e) =>
+            setConfig({ ...config, hysteresis: Number(e.target.value) })
+          }
+        />
+      </div>
+      <div className="slider-group">
+        <label>Variação máxima por ajuste: {toPercent(config.ramp_step)}%</label>
+        <input
+          type="range"
+          min="1"
+          max="100"
+          value={toPercent(config.ramp_step)}
+          onChange={(e) =>
+            setConfig({
+              ...config,
+              ramp_step: Math.max(1, toBoost(Number(e.target.value))),
+            })
+          }
+        />
+      </div>
+      <div className="slider-group">
+        <label>
+          Intervalo mínimo entre ajustes:{" "}
+          {(config.min_write_interval_ms / 1000).toFixed(1)}s
+        </label>
+        <input
+          type="range"
+          min="500"
+          max="15000"
+          step="500"
+          value={config.min_write_interval_ms}
+          onChange={(e) =>
+            setConfig({
+              ...config,
+              min_write_interval_ms: Number(e.target.value),
+            })
+          }
+        />
+      </div>
+
+      <button
+        className="apply-btn"
+        onClick={() => apply({ ...config, enabled: true })}
+        disabled={fanControlLimited}
+      >
+        {running ? "Atualizar Curva" : "Ativar Curva"}
+      </button>
+      {running && (
+        <button
+          className="apply-btn"
+          onClick={() => apply({ ...config, enabled: false })}
+        >
+          Desativar Curva
+        </button>
+      )}
+    </div>
+  );
+}
@@ -6,7 +6,7 @@ import {
   requestPermission,
   sendNotification,
 } from "@tauri-apps/plugin-notification";
-import { DeviceInfo, SensorData } from "../types";
+import { DeviceInfo, Intervention, SensorData } from "../types";
 // ... imports
 
 // ... inside component
@@ -15,6 +15,10 @@ import { DeviceInfo, SensorData } from "../types";
 
 // ... inside component
 import { FanControl } from "./FanControl";
+import { FanCurveControl } from "./FanCurveControl";
+import { FanCalibrationPanel } from "./FanCalibrationPanel";
+import { ThermalRestoreSettings } from "./ThermalRestoreSettings";
+import { ThermalWatchdogSettings } from "./ThermalWatchdogSettings";
 import { SensorDisplay } from "./SensorDisplay";
 import { usePersistedSettings } from "../hooks/usePersistedSettings";
 
@@ -41,6 +45,8 @@ export function PowerControl({
   // Use persisted settings or defaults
   const cpuFan = settings?.cpuFan ?? 50;
   const gpuFan = settings?.gpuFan ?? 50;
+  const cpuRpm = settings?.cpuRpm ?? 3000;
+  const gpuRpm = settings?.gpuRpm ?? 3000;
   const currentMode = settings?.currentMode ?? "USTT_Balanced";
 
   const readSensors = async () => {
@@ -61,6 +67,27 @@ export function PowerControl({
     setIsTurbo(persistedTurbo);
   }, [deviceInfo?.power_supported, deviceInfo?.turbo_enabled, settings?.isTurbo]);
 
+  // Sliders follow the boost the EC reports on the first reading
+  const boostSynced = useRef(false);
+  useEffect(() => {
+    if (boostSynced.current || !sensorData) return;
+    boostSynced.current = true;
+    if (sensorData.fan1_boost) {
+      updateSetting("cpuFan", sensorData.fan1_boost.percent);
+    }
+    if (sensorData.fan2_boost) {
+      updateSetting("gpuFan", sensorData.fan2_boost.percent);
+    }
+  }, [sensorData]);
+
+  // Reflect the mode the firmware is actually in at startup
+  useEffect(() => {
+    const mode = deviceInfo?.current_power_mode;
+    if (mode && mode !== settings?.currentMode) {
+      updateSetting("currentMode", mode);
+    }
+  }, [deviceInfo?.current_power_mode]);
+
   // Sensor reading interval
   useEffect(() => {
     if (!deviceInfo?.power_supported) return;
@@ -95,6 +122,31 @@ export function PowerControl({
     };
   }, [updateSetting]);
 
+  // The thermal watchdog raised the fans or left Manual mode on its own
+  useEffect(() => {
+    const unlisten = listen<Intervention>("thermal-intervention", (event) => {
+      const intervention = event.payload;
+      setIsTurbo(false);
+      updateSetting("isTurbo", false);
+      if (intervention.action === "mode_reverted") {
+        updateSetting("currentMode", intervention.mode);
+        showStatus(
+          `🔥 ${intervention.temp}°C: modo ${intervention.mode} restaurado`,
+          true
+        );
+      } else {
+        showStatus(
+          `🔥 ${intervention.temp}°C: ventiladores acelerados pela proteção térmica`,
+          true
+        );
+      }
+    });
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [updateSetting]);
+
   const setPowerMode = async (mode: string) => {
     try {
       const result: string = await invoke("set_power_mode", { mode });
@@ -187,6 +239,20 @@ export function PowerControl({
     }
   };
 
+  // Closed loop: the backend adjusts boost until the fans reach these RPMs
+  const applyFanRpm = async () => {
+    try {
+      const result: string = await invoke("set_fan_rpm_target", {
+        params: { cpu_rpm: cpuRpm, gpu_rpm: gpuRpm },
+      });
+      setIsTurbo(false);
+      updateSetting("isTurbo", false);
+      showStatus(result);
+    } catch (error) {
+      showStatus("Erro: " + String(error), true);
+    }
+  };
+
   if (!deviceInfo?.power_supported) {
     return (
       <section className="card glass-panel">
@@ -249,6 +315,11 @@ export function PowerControl({
 
       <SensorDisplay sensors={sensorData} />
 
+      <FanCurveControl
+        showStatus={showStatus}
+        fanControlLimited={deviceInfo?.fan_control_limited}
+      />
+
       {/* Hidden manual control for safety, can be re-enabled if needed */}
       {currentMode === "Manual" && (
         <FanControl
@@ -257,9 +328,22 @@ export function PowerControl({
           onFan1Change={handleCpuFanChange}
           onFan2Change={handleGpuFanChange}
           onApply={applyFanSpeeds}
+          rpm1={cpuRpm}
+          rpm2={gpuRpm}
+          onRpm1Change={(value) => updateSetting("cpuRpm", value)}
+          onRpm2Change={(value) => updateSetting("gpuRpm", value)}
+          onApplyRpm={applyFanRpm}
           fanControlLimited={deviceInfo?.fan_control_limited}
         />
       )}
+
+      <FanCalibrationPanel
+        showStatus={showStatus}
+        fanControlLimited={deviceInfo?.fan_control_limited}
+      />
+
+      <ThermalWatchdogSettings showStatus={showStatus} />
+      <ThermalRestoreSettings showStatus={showStatus} />
     </section>
   );
 }
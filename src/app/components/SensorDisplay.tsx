@@ -4,9 +4,16 @@ interface SensorDisplayProps {
   sensors: SensorData | null;
 }
 
+// Ids of the readings behind the fixed fields of SensorData
+const PRIMARY_SENSOR_IDS = ["wmax:cpu_temp", "wmax:gpu_temp", "wmax:fan1", "wmax:fan2"];
+
 export function SensorDisplay({ sensors }: SensorDisplayProps) {
   if (!sensors) return null;
 
+  // The primary WMAX values are already shown above; list everything else
+  // (extra WMAX fans and thermistors, hwmon sensors) below them
+  const extra = (sensors.sensors ?? []).filter((s) => !PRIMARY_SENSOR_IDS.includes(s.id));
+
   return (
     <div className="section">
       <h3>Sensores</h3>
@@ -27,6 +34,17 @@ export function SensorDisplay({ sensors }: SensorDisplayProps) {
           <span className="sensor-label">GPU Temp</span>
           <span className="sensor-value">{sensors.gpu_temp}°C</span>
         </div>
+        {extra.map((s) => (
+          <div className="sensor-item" key={s.id} title={s.source}>
+            <span className="sensor-label">
+              {s.label} ({s.source})
+            </span>
+            <span className="sensor-value">
+              {s.kind === "temperature" ? s.value.toFixed(1) : Math.round(s.value)}
+              {s.unit === "°C" ? s.unit : ` ${s.unit}`}
+            </span>
+          </div>
+        ))}
       </div>
     </div>
   );
@@ -0,0 +1,64 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { RestoreConfig } from "../types";
+
+interface ThermalRestoreSettingsProps {
+  showStatus: (message: string, isError?: boolean) => void;
+}
+
+export function ThermalRestoreSettings({
+  showStatus,
+}: ThermalRestoreSettingsProps) {
+  const [config, setConfig] = useState<RestoreConfig | null>(null);
+
+  useEffect(() => {
+    invoke<RestoreConfig>("get_restore_config")
+      .then(setConfig)
+      .catch((error) =>
+        showStatus("Erro ao carregar restauração: " + String(error), true)
+      );
+  }, []);
+
+  const save = async (next: RestoreConfig) => {
+    try {
+      const result: string = await invoke("set_restore_config", {
+        config: next,
+      });
+      setConfig(next);
+      showStatus(result);
+    } catch (error) {
+      showStatus("Erro: " + String(error), true);
+    }
+  };
+
+  if (!config) return null;
+
+  return (
+    <div className="section">
+      <h3>Ao Sair do Aplicativo</h3>
+      <div className="slider-group">
+        <label>
+          <input
+            type="checkbox"
+            checked={config.enabled}
+            onChange={(e) => save({ ...config, enabled: e.target.checked })}
+          />{" "}
+          Sair do modo Manual e voltar ao último modo automático
+        </label>
+      </div>
+      <div className="slider-group">
+        <label>
+          <input
+            type="checkbox"
+            checked={config.watchdog_process}
+            onChange={(e) =>
+              save({ ...config, watchdog_process: e.target.checked })
+            }
+            disabled={!config.enabled}
+          />{" "}
+          Usar processo de vigia (cobre kill -9 e encerramentos forçados)
+        </label>
+      </div>
+    </div>
+  );
+}
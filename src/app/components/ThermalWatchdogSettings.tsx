@@ -0,0 +1,83 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { WatchdogConfig } from "../types";
+
+interface ThermalWatchdogSettingsProps {
+  showStatus: (message: string, isError?: boolean) => void;
+}
+
+export function ThermalWatchdogSettings({
+  showStatus,
+}: ThermalWatchdogSettingsProps) {
+  const [config, setConfig] = useState<WatchdogConfig | null>(null);
+
+  useEffect(() => {
+    invoke<WatchdogConfig>("get_thermal_watchdog")
+      .then(setConfig)
+      .catch((error) =>
+        showStatus("Erro ao carregar proteção térmica: " + String(error), true)
+      );
+  }, []);
+
+  const save = async (next: WatchdogConfig) => {
+    try {
+      const result: string = await invoke("set_thermal_watchdog", {
+        config: next,
+      });
+      setConfig(next);
+      showStatus(result);
+    } catch (error) {
+      showStatus("Erro: " + String(error), true);
+    }
+  };
+
+  if (!config) return null;
+
+  return (
+    <div className="section">
+      <h3>Proteção Térmica (modo Manual)</h3>
+      <div className="slider-group">
+        <label>
+          <input
+            type="checkbox"
+            checked={config.enabled}
+            onChange={(e) => save({ ...config, enabled: e.target.checked })}
+          />{" "}
+          Intervir quando a temperatura subir demais
+        </label>
+      </div>
+      <div className="slider-group">
+        <label>
+          Acelerar ventiladores a partir de: {config.warn_temp}°C
+        </label>
+        <input
+          type="range"
+          min="60"
+          max={config.critical_temp - 1}
+          value={config.warn_temp}
+          onChange={(e) =>
+            setConfig({ ...config, warn_temp: Number(e.target.value) })
+          }
+          onMouseUp={() => save(config)}
+          disabled={!config.enabled}
+        />
+      </div>
+      <div className="slider-group">
+        <label>
+          Voltar ao modo automático a partir de: {config.critical_temp}°C
+        </label>
+        <input
+          type="range"
+          min={config.warn_temp + 1}
+          max="105"
+          value={config.critical_temp}
+          onChange={(e) =>
+            setConfig({ ...config, critical_temp: Number(e.target.value) })
+          }
+          onMouseUp={() => save(config)}
+          disabled={!config.enabled}
+        />
+      </div>
+    </div>
+  );
+}
@@ -11,6 +11,8 @@ export interface PersistedSettings {
   duration: number;
   currentLedMode: string;
   isTurbo?: boolean;
+  cpuRpm?: number;
+  gpuRpm?: number;
   zone0?: [number, number, number];
   zone1?: [number, number, number];
   zone2?: [number, number, number];
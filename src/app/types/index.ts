@@ -3,8 +3,20 @@ export interface DeviceInfo {
   keyboard_supported: boolean;
   power_supported: boolean;
   power_modes: string[];
+  current_power_mode?: string | null;
+  g_mode_enabled?: boolean;
   fan_control_limited?: boolean;
   turbo_enabled?: boolean;
+  hardware?: HardwareInfo;
+}
+
+export interface HardwareInfo {
+  sys_vendor: string;
+  product_name: string;
+  product_sku: string;
+  board_name: string;
+  bios_version: string;
+  cpu_vendor: string;
 }
 
 export interface SensorData {
@@ -12,6 +24,23 @@ export interface SensorData {
   fan2_rpm: number;
   cpu_temp: number;
   gpu_temp: number;
+  fan1_boost?: FanState | null;
+  fan2_boost?: FanState | null;
+  sensors?: SensorReading[];
+}
+
+export interface SensorReading {
+  id: string;
+  label: string;
+  kind: 'temperature' | 'fan';
+  value: number;
+  unit: string;
+  source: string;
+}
+
+export interface FanState {
+  boost: number;
+  percent: number;
 }
 
 export interface PresetColor {
@@ -36,3 +65,81 @@ export type LedMode =
   | "spectrum"
   | "rainbow"
   | "off";
+
+export type TempSource = "cpu" | "gpu" | "max";
+
+export interface CurvePoint {
+  temp: number;
+  boost: number;
+}
+
+export interface FanCurve {
+  source: TempSource;
+  points: CurvePoint[];
+}
+
+export interface FanCurveConfig {
+  enabled: boolean;
+  cpu_fan: FanCurve;
+  gpu_fan: FanCurve;
+  hysteresis: number;
+  ramp_step: number;
+  min_write_interval_ms: number;
+  poll_interval_ms: number;
+}
+
+export interface FanCurveStatus {
+  config: FanCurveConfig;
+  running: boolean;
+}
+
+export interface CalibrationPoint {
+  boost: number;
+  rpm: number | null;
+  settled: boolean;
+}
+
+export interface FanCalibration {
+  fan: string;
+  points: CalibrationPoint[];
+}
+
+export type Anomaly =
+  | { kind: "no_reading"; fan: string }
+  | { kind: "not_spinning"; fan: string; boost: number }
+  | { kind: "not_responding"; fan: string; low_rpm: number; high_rpm: number }
+  | { kind: "asymmetry"; boost: number; fan1_rpm: number; fan2_rpm: number };
+
+export interface CalibrationReport {
+  machine: string;
+  model: string;
+  hardware: HardwareInfo;
+  created_at: number;
+  fans: FanCalibration[];
+  anomalies: Anomaly[];
+}
+
+export interface CalibrationProgress {
+  step: number;
+  steps: number;
+  boost: number;
+}
+
+export interface WatchdogConfig {
+  enabled: boolean;
+  warn_temp: number;
+  warn_boost: number;
+  critical_temp: number;
+  fallback_mode: string;
+  poll_interval_ms: number;
+}
+
+export interface RestoreConfig {
+  enabled: boolean;
+  watchdog_process: boolean;
+  fallback_mode: string;
+}
+
+export type Intervention =
+  | { action: "boost_raised"; temp: number; boost: number }
+  | { action: "mode_reverted"; temp: number; mode: string };